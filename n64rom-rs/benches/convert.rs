@@ -0,0 +1,26 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use n64rom::convert;
+use n64rom::rom::Endianness;
+
+/// Buffer size of the largest roms, 64 MiB.
+const SIZE: usize = 1024 * 1024 * 64;
+
+fn bench_convert(c: &mut Criterion) {
+    let orders = [Endianness::Big, Endianness::Little, Endianness::Mixed];
+    let mut buf: Vec<u8> = (0..SIZE).map(|i| i as u8).collect();
+    let mut group = c.benchmark_group("convert");
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.sample_size(20);
+    for &current in &orders {
+        for &target in orders.iter().filter(|&&target| target != current) {
+            let id = BenchmarkId::from_parameter(format!("{:?}->{:?}", current, target));
+            group.bench_function(id, |b| {
+                b.iter(|| convert::convert(&mut buf, current, target).unwrap());
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_convert);
+criterion_main!(benches);
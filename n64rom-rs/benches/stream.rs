@@ -0,0 +1,29 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use n64rom::rom::Endianness;
+use n64rom::stream::{Reader, BUFFER_SIZE};
+use std::io::Read;
+
+/// Buffer size of the largest roms, 64 MiB.
+const SIZE: usize = 1024 * 1024 * 64;
+
+fn bench_reader(c: &mut Criterion) {
+    let data: Vec<u8> = (0..SIZE).map(|i| i as u8).collect();
+    let mut output = vec![0; SIZE];
+    let mut group = c.benchmark_group("reader");
+    group.throughput(Throughput::Bytes(SIZE as u64));
+    group.sample_size(10);
+    for &capacity in &[1024 * 4, BUFFER_SIZE, 1024 * 64, 1024 * 1024] {
+        let id = BenchmarkId::from_parameter(format!("{} KiB", capacity / 1024));
+        group.bench_function(id, |b| {
+            b.iter(|| {
+                let mut input = &data[..];
+                let mut reader = Reader::with_buffer_size(&mut input, Endianness::Little, capacity);
+                reader.read_exact(&mut output).unwrap();
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_reader);
+criterion_main!(benches);
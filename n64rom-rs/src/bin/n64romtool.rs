@@ -7,23 +7,33 @@ use thiserror::Error;
 
 use n64rom::convert::{self, ConvertStatus};
 use n64rom::header::Header;
-use n64rom::rom::{Endianness, FileExt, Rom};
+use n64rom::rom::{CrcReport, Endianness, FileExt, Rom};
 use n64rom::stream::Writer;
 use n64rom::util::{self, FileSize, MEBIBYTE};
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Error)]
 enum Error {
     #[error("{0}")]
     ConvertError(#[from] n64rom::convert::Error),
     /// Invalid CRC values.
-    #[error("Bad CRC values, expected: ({0:#08X}, {1:#08X})")]
-    CRCError(u32, u32),
+    #[error("{0}")]
+    CRCError(CrcReport),
+    /// CRC values are zero, such as in a freshly assembled rom.
+    #[error("CRC values are not set, expected: ({0:#010X}, {1:#010X})")]
+    CRCNotSetError(u32, u32),
     /// Error parsing Header.
     #[error("{0}")]
     HeaderError(#[from] n64rom::header::Error),
     /// IO error.
     #[error("{0}")]
     IOError(#[from] io::Error),
+    /// Error reading Rom.
+    #[error("{0}")]
+    RomError(#[from] n64rom::rom::Error),
+    /// CRC values changed by conversion.
+    #[error("Verification failed, CRC values changed from ({0:#08X}, {1:#08X}) to ({2:#08X}, {3:#08X})")]
+    VerifyError(u32, u32, u32, u32),
 }
 
 fn main() -> Result<(), Error> {
@@ -34,6 +44,10 @@ fn main() -> Result<(), Error> {
         .subcommand(
             Command::new("show")
                 .about("Show details about a rom file")
+                .arg(Arg::new("hashes")
+                    .long("hashes")
+                    .takes_value(false)
+                    .help("Also show SHA-1 and MD5 hashes of the rom, which requires reading all data."))
                 .arg(Arg::new("file")
                     .required(true)
                     .help("Rom file"))
@@ -58,9 +72,18 @@ fn main() -> Result<(), Error> {
                     .long("ext")
                     .takes_value(false)
                     .help("Update the ROM file extension for the corresponding byte order"))
+                .arg(Arg::new("auto-ext")
+                    .long("auto-ext")
+                    .takes_value(false)
+                    .conflicts_with("in-place")
+                    .help("Replace the output ROM file extension with the one for the corresponding byte order"))
+                .arg(Arg::new("verify")
+                    .long("verify")
+                    .takes_value(false)
+                    .help("Verify that CRC values of the converted ROM match those of the input ROM."))
                 .arg(Arg::new("order")
                     .takes_value(true)
-                    .possible_values(&["big", "little", "mixed"])
+                    .possible_values(["big", "little", "mixed"])
                     .required(true)
                     .help("Byte order to convert to"))
                 .arg(Arg::new("input")
@@ -70,6 +93,16 @@ fn main() -> Result<(), Error> {
                     .required_unless_present("in-place")
                     .help("Output rom file"))
         )
+        .subcommand(
+            Command::new("diff")
+                .about("Compare the head of two rom files, such as to verify a bootchip swap")
+                .arg(Arg::new("first")
+                    .required(true)
+                    .help("First rom file"))
+                .arg(Arg::new("second")
+                    .required(true)
+                    .help("Second rom file"))
+        )
         .subcommand(
             Command::new("correct")
                 .about("Correct the CRC values of a rom file")
@@ -81,13 +114,13 @@ fn main() -> Result<(), Error> {
 
     match main_with_args(&matches) {
         Ok(()) => Ok(()),
-        Err(Error::HeaderError(err)) => {
+        Err(Error::HeaderError(err)) | Err(Error::RomError(n64rom::rom::Error::HeaderError(err))) => {
             println!("Error: {}, are you sure this is a rom file?", err);
             process::exit(1);
         }
-        Err(Error::CRCError(crc1, crc2)) => {
-            // Display default CRCError message
-            println!("{}", Error::CRCError(crc1, crc2));
+        Err(err @ Error::CRCError(..)) | Err(err @ Error::CRCNotSetError(..)) => {
+            // Display default CRC error message
+            println!("{}", err);
             process::exit(1);
         }
         Err(err) => {
@@ -97,10 +130,13 @@ fn main() -> Result<(), Error> {
     }
 }
 
-fn load_rom(path: &str, with_body: bool) -> Result<(Rom, File), Error> {
-    let in_path = Path::new(path);
-    let mut file = File::open(in_path)?;
-    let rom = Rom::read_with_body(&mut file, with_body)?;
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn load_rom(path: impl AsRef<Path>, with_body: bool) -> Result<(Rom, File), Error> {
+    let mut file = File::open(path)?;
+    let rom = Rom::read_buffered(&mut file, with_body)?;
     Ok((rom, file))
 }
 
@@ -111,36 +147,56 @@ fn load_rom_rw(path: &str) -> Result<(Rom, File), Error> {
     Ok((rom, file))
 }
 
+/// Verify that the computed CRC values of the rom file match the expected values, if any.
+fn verify_crcs(path: impl AsRef<Path>, expected: Option<(u32, u32)>) -> Result<(), Error> {
+    if let Some(expected) = expected {
+        let (rom, _) = load_rom(path, true)?;
+        let (_, actual) = rom.check_crc();
+        if actual != expected {
+            return Err(Error::VerifyError(expected.0, expected.1, actual.0, actual.1));
+        }
+    }
+    Ok(())
+}
+
 fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
 
     match matches.subcommand() {
         Some(("check", matches)) => {
             let path = matches.value_of("file").unwrap();
-            let (rom, _) = load_rom(&path, true)?;
+            let (rom, _) = load_rom(path, true)?;
 
-            let (result, crcs) = rom.check_crc();
-            if result {
+            let report = rom.crc_report();
+            if report.matches {
                 println!("Correct!");
                 Ok(())
+            } else if !rom.header.has_crc() {
+                Err(Error::CRCNotSetError(report.computed.0, report.computed.1))
             } else {
-                Err(Error::CRCError(crcs.0, crcs.1))
+                if let Some(cic) = rom.detect_cic_by_crc() {
+                    println!("Note: Header CRC values match {}, but IPL3 is {}", cic, rom.ipl3);
+                }
+                Err(Error::CRCError(report))
             }
         }
         Some(("convert", matches)) => {
             // Get variables from arguments.
             let in_place = matches.is_present("in-place");
             let input = matches.value_of("input").unwrap();
-            let order = match matches.value_of("order").unwrap() {
-                "big" => Endianness::Big,
-                "little" => Endianness::Little,
-                "mixed" => Endianness::Mixed,
-                _ => unreachable!(),
+            let order: Endianness = matches.value_of("order").unwrap().parse()?;
+            // Compute CRC values of the input before it may be converted in-place.
+            let verify = if matches.is_present("verify") {
+                let (rom, _) = load_rom(input, true)?;
+                Some(rom.check_crc().1)
+            } else {
+                None
             };
             // Perform rom convert.
             let result = if in_place {
                 // Update ROM file in-place.
                 let use_ext = matches.is_present("ext");
-                let (result, _) = convert::convert_rom_path_inplace(&input, order)?;
+                let (result, _) = convert::convert_rom_path_inplace(input, order)?;
+                verify_crcs(input, verify)?;
                 if use_ext {
                     let ext = FileExt::from_endianness(order).unwrap();
                     util::update_file_extension(input, ext.as_str())?;
@@ -148,10 +204,16 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
                 result
             } else {
                 // Convert to separate output ROM file.
-                let output = matches.value_of("output").unwrap();
-                let (result, _) = convert::convert_rom_path(&input, &output, order)?;
+                let mut output = Path::new(matches.value_of("output").unwrap()).to_path_buf();
+                if matches.is_present("auto-ext") {
+                    let ext = FileExt::from_endianness(order).unwrap();
+                    output.set_extension(ext.as_str());
+                }
+                let (result, _) = convert::convert_rom_path(input, &output, order)?;
+                verify_crcs(&output, verify)?;
                 result
             };
+
             if matches!(result, ConvertStatus::AlreadyConverted) {
                 println!("Rom file is already in {} byte order.", order);
             } else {
@@ -161,7 +223,7 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
         }
         Some(("correct", matches)) => {
             let path = matches.value_of("file").unwrap();
-            let (mut rom, mut file) = load_rom_rw(&path)?;
+            let (mut rom, mut file) = load_rom_rw(path)?;
 
             if rom.correct_crc() {
                 println!("Rom CRC values are already correct!");
@@ -178,10 +240,30 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
                 Ok(())
             }
         }
+        Some(("diff", matches)) => {
+            let (first, _) = load_rom(matches.value_of("first").unwrap(), false)?;
+            let (second, _) = load_rom(matches.value_of("second").unwrap(), false)?;
+            let cic_name = |rom: &Rom| rom.ipl3.cic().map_or("Unknown", |cic| cic.name());
+
+            if first.header == second.header {
+                println!("Header: Identical");
+            } else {
+                println!("Header: Different");
+            }
+            if first.ipl3.eq_bootcode(&second.ipl3) {
+                println!("IPL3: Identical ({})", cic_name(&first));
+            } else if first.ipl3.cic() != second.ipl3.cic() {
+                println!("IPL3: Different, CIC changed from {} to {}", cic_name(&first), cic_name(&second));
+            } else {
+                println!("IPL3: Different bootcode for {}", cic_name(&first));
+            }
+            Ok(())
+        }
         Some(("show", matches)) => {
             // Read rom with only head (header & IPL3)
             let path = matches.value_of("file").unwrap();
-            let (rom, file) = load_rom(&path, false)?;
+            let hashes = matches.is_present("hashes");
+            let (rom, file) = load_rom(path, hashes)?;
 
             // For efficiency, instead of reading all data to determine rom size, check file metadata
             let metadata = file.metadata()?;
@@ -198,7 +280,18 @@ fn main_with_args(matches: &ArgMatches) -> Result<(), Error> {
             };
 
             println!("{}", rom);
-            println!("  Rom Size: {}", &sizetext);
+            println!("  Rom Size: {}", sizetext);
+            if let Some(cic) = rom.ipl3.cic() {
+                println!("  Boot Chip: {}", cic.name());
+            }
+            if let Some(version) = rom.header.sdk_version() {
+                let number = version >> 8;
+                println!("  SDK Version: {}.{}{}", number / 10, number % 10, (version as u8) as char);
+            }
+            if hashes {
+                println!("  SHA-1: {}", to_hex(&rom.sha1()));
+                println!("  MD5: {}", to_hex(&rom.md5()));
+            }
 
             Ok(())
         }
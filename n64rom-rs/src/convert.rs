@@ -1,4 +1,9 @@
-use std::fs::{File, OpenOptions};
+//! Conversion of rom data between byte orders.
+//!
+//! Swaps to or from big-endian are performed on whole `u32` words, which is faster than swapping individual bytes
+//! (see the `convert` benchmark).
+
+use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read, Seek, SeekFrom, Write};
 use std::path::Path;
 use thiserror::Error;
@@ -6,6 +11,9 @@ use thiserror::Error;
 use crate::header::Magic;
 use crate::rom::{Endianness, Rom, MAX_SIZE};
 
+/// Default size of chunks used when converting streams, in bytes.
+pub const CHUNK_SIZE: usize = 0x10000;
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Buffer length must be 4-byte aligned to perform conversion, instead found length: {0}")]
@@ -23,7 +31,7 @@ pub enum Error {
 }
 
 pub fn validate_alignment(value: usize) -> Result<(), Error> {
-    if value % 4 == 0 {
+    if value.is_multiple_of(4) {
         Ok(())
     } else {
         Err(Error::AlignmentError(value))
@@ -42,14 +50,15 @@ pub fn validate_rom_file_size(filesize: u64) -> Result<usize, Error> {
 
 /// Perform 4-byte swap between Big Endian and Little Endian.
 fn swap_big_little(buf: &mut [u8]) {
-    buf.swap(0, 3);
-    buf.swap(1, 2);
+    let w = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]).swap_bytes();
+    buf.copy_from_slice(&w.to_ne_bytes());
 }
 
 /// Perform 4-byte swap between Big Endian and Mixed Endian.
 fn swap_big_mixed(buf: &mut [u8]) {
-    buf.swap(0, 1);
-    buf.swap(2, 3);
+    let w = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let w = w.swap_bytes().rotate_left(16);
+    buf.copy_from_slice(&w.to_ne_bytes());
 }
 
 /// Perform 4-byte swap between Little Endian and Mixed Endian.
@@ -145,7 +154,7 @@ fn convert_with<T: RomConvert>(buf: &mut [u8], target: Endianness) -> ConvertSta
 
 /// Convert data from the current `Endianness` to a target `Endianness`.
 pub fn convert(buf: &mut [u8], current: Endianness, target: Endianness) -> Result<ConvertStatus, Error> {
-    if buf.len() % 4 == 0 {
+    if buf.len().is_multiple_of(4) {
         let result = match current {
             Endianness::Big =>
                 convert_with::<BigEndianConverter>(buf, target),
@@ -210,7 +219,56 @@ pub fn convert_rom(rom: &mut Rom, target: Endianness) -> Result<ConvertStatus, E
     convert(&mut rom.image, order, target)
 }
 
+/// Fill buffer from reader until full or end of stream, returning the amount read.
+fn read_chunk<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut total = 0;
+    while total < buf.len() {
+        match reader.read(&mut buf[total..]) {
+            Ok(0) => break,
+            Ok(amount) => total += amount,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(total)
+}
+
+/// Convert all data from a reader to a target `Endianness`, writing each chunk of `chunk_size` bytes as it is converted.
+///
+/// Only a single chunk is held in memory at a time. The chunk size and total length of data must be 4-byte aligned.
+pub fn convert_stream<R: Read, W: Write>(
+    reader: &mut R,
+    writer: &mut W,
+    current: Endianness,
+    target: Endianness,
+    chunk_size: usize,
+) -> Result<(ConvertStatus, usize), Error> {
+    if chunk_size == 0 {
+        return Err(Error::AlignmentError(chunk_size));
+    }
+    validate_alignment(chunk_size)?;
+    let mut buf = vec![0; chunk_size];
+    let mut total = 0;
+    let mut result = ConvertStatus::AlreadyConverted;
+    loop {
+        let amount = read_chunk(reader, &mut buf)?;
+        if amount == 0 {
+            break;
+        }
+        total += amount;
+        // Only the final chunk may be short, so an unaligned chunk means unaligned data.
+        if !amount.is_multiple_of(4) {
+            return Err(Error::AlignmentError(total));
+        }
+        result = convert(&mut buf[..amount], current, target)?;
+        writer.write_all(&buf[..amount])?;
+    }
+    Ok((result, total))
+}
+
 /// Convenience function to convert a given rom `File` to the specified `Endianness`.
+///
+/// Data is converted in chunks of `CHUNK_SIZE` bytes, so the full rom is never held in memory.
 pub fn convert_rom_file(in_file: &mut File, out_file: &mut File, target: Endianness) -> Result<(ConvertStatus, usize), Error> {
     in_file.seek(SeekFrom::Start(0))?;
 
@@ -220,31 +278,139 @@ pub fn convert_rom_file(in_file: &mut File, out_file: &mut File, target: Endiann
 
     // TODO: Warn about converting to same endianness (this will result in copying the file).
 
-    // Determine filesize in attempt to prevent buffer from re-allocating.
+    // Validate filesize before writing any output.
     let filesize = in_file.metadata()?.len();
     let size = validate_rom_file_size(filesize)?;
 
-    // Read file into memory to perform conversion.
-    let mut contents = Vec::with_capacity(size);
     let mut handle = in_file.take(filesize);
-    let read_amount = handle.read_to_end(&mut contents)?;
+    let (result, read_amount) = convert_stream(&mut handle, out_file, order, target, CHUNK_SIZE)?;
 
     if size != read_amount {
         return Err(Error::FileReadError(size, read_amount))
     }
 
-    // Perform endianness conversion.
-    let result = convert(&mut contents, order, target)?;
-
-    // Write resulting contents.
-    out_file.write_all(&contents)?;
-
     Ok((result, size))
 }
 
 /// Convenience function to convert a rom file at a given `Path` to the specified `Endianness`.
+///
+/// If both paths refer to the same file, it is converted in-place instead, as truncating the output would otherwise
+/// destroy the input before it is read.
 pub fn convert_rom_path(in_path: impl AsRef<Path>, out_path: impl AsRef<Path>, target: Endianness) -> Result<(ConvertStatus, usize), Error> {
+    // Output may not exist yet, in which case it cannot be the input.
+    if let (Ok(input), Ok(output)) = (fs::canonicalize(&in_path), fs::canonicalize(&out_path)) {
+        if input == output {
+            return convert_rom_path_inplace(in_path, target);
+        }
+    }
     let mut in_file = OpenOptions::new().read(true).open(in_path)?;
     let mut out_file = OpenOptions::new().write(true).create(true).truncate(true).open(out_path)?;
     convert_rom_file(&mut in_file, &mut out_file, target)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    const ORDERS: [Endianness; 3] = [Endianness::Big, Endianness::Little, Endianness::Mixed];
+
+    /// Reference conversion of a single word, by byte positions of the big-endian word in each order.
+    fn reference(word: [u8; 4], current: Endianness, target: Endianness) -> [u8; 4] {
+        let positions = |order| match order {
+            Endianness::Big => [0, 1, 2, 3],
+            Endianness::Little => [3, 2, 1, 0],
+            Endianness::Mixed => [1, 0, 3, 2],
+        };
+        // Recover the big-endian word, then lay it out in the target order.
+        let mut big = [0; 4];
+        for (&byte, &position) in word.iter().zip(&positions(current)) {
+            big[position] = byte;
+        }
+        let mut result = [0; 4];
+        for (byte, &position) in result.iter_mut().zip(&positions(target)) {
+            *byte = big[position];
+        }
+        result
+    }
+
+    #[test]
+    fn convert_matches_reference() {
+        let data: Vec<u8> = (0..=255).collect();
+        for &current in &ORDERS {
+            for &target in &ORDERS {
+                let mut buf = data.clone();
+                convert(&mut buf, current, target).unwrap();
+                let expected: Vec<u8> = data
+                    .chunks_exact(4)
+                    .flat_map(|word| reference([word[0], word[1], word[2], word[3]], current, target))
+                    .collect();
+                assert_eq!(buf, expected, "{:?} -> {:?}", current, target);
+            }
+        }
+    }
+
+    #[test]
+    fn convert_rom_path_same_file() {
+        let path = std::env::temp_dir().join(format!("n64rom-convert-{}.z64", std::process::id()));
+        let mut image = vec![0; crate::rom::HEAD_SIZE + 0x100];
+        image[..4].copy_from_slice(Magic::new().as_ref());
+        image[0x1000..0x1004].copy_from_slice(&[1, 2, 3, 4]);
+        fs::write(&path, &image).unwrap();
+
+        let result = convert_rom_path(&path, &path, Endianness::Little);
+        let converted = fs::read(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(matches!(result.unwrap(), (ConvertStatus::Complete, size) if size == image.len()));
+        assert_eq!(converted.len(), image.len());
+        assert_eq!(&converted[0x1000..0x1004], &[4, 3, 2, 1]);
+    }
+
+    #[test]
+    fn convert_stream_chunks() {
+        let data: Vec<u8> = (0..=255).collect();
+        for &current in &ORDERS {
+            for &target in &ORDERS {
+                let mut expected = data.clone();
+                convert(&mut expected, current, target).unwrap();
+                // Chunk sizes which do and do not divide the data length.
+                for &chunk_size in &[4, 12, 0x100, 0x400] {
+                    let mut output = Vec::new();
+                    let (_, size) = convert_stream(&mut &data[..], &mut output, current, target, chunk_size).unwrap();
+                    assert_eq!(size, data.len());
+                    assert_eq!(output, expected, "{:?} -> {:?} ({})", current, target, chunk_size);
+                }
+            }
+        }
+
+        let mut output = Vec::new();
+        let result = convert_stream(&mut &data[..], &mut output, Endianness::Big, Endianness::Little, 6);
+        assert!(matches!(result, Err(Error::AlignmentError(6))));
+        let result = convert_stream(&mut &data[..0x12], &mut output, Endianness::Big, Endianness::Little, 8);
+        assert!(matches!(result, Err(Error::AlignmentError(0x12))));
+    }
+
+    /// Random buffers with a length that is a multiple of 4.
+    fn aligned_buffer() -> impl Strategy<Value = Vec<u8>> {
+        prop::collection::vec(any::<[u8; 4]>(), 0..256).prop_map(|words| words.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn convert_round_trip(data in aligned_buffer()) {
+            for &current in &ORDERS {
+                for &target in &ORDERS {
+                    let mut buf = data.clone();
+                    convert(&mut buf, current, target).unwrap();
+                    let expected: Vec<u8> = data
+                        .chunks_exact(4)
+                        .flat_map(|word| reference([word[0], word[1], word[2], word[3]], current, target))
+                        .collect();
+                    prop_assert_eq!(&buf, &expected, "{:?} -> {:?}", current, target);
+                    convert(&mut buf, target, current).unwrap();
+                    prop_assert_eq!(&buf, &data, "{:?} -> {:?} -> {:?}", current, target, current);
+                }
+            }
+        }
+    }
+}
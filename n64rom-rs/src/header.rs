@@ -13,11 +13,15 @@ use crate::rom::Endianness;
 pub enum Error {
     #[error("{0}")]
     IOError(#[from] io::Error),
+    #[error("Invalid header size: 0x{0:X}, expected 0x40")]
+    InvalidSize(usize),
     #[error("Unknown byte order from magic ({0:#08X})")]
     UnknownByteOrder(u32),
+    #[error("Invalid header word index: {0}, expected less than 16")]
+    InvalidWordIndex(usize),
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
 /// Represents the initial four bytes of the rom header.
 ///
 /// This value is often used to infer the byte order of the rom data.
@@ -115,7 +119,7 @@ impl AsRef<[u8; 4]> for Magic {
 }
 
 /// Media format of rom.
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Default, Eq, Hash, PartialEq)]
 pub struct Media([u8; 4]);
 
 impl Media {
@@ -147,7 +151,11 @@ impl AsRef<[u8; 4]> for Media {
     }
 }
 
-#[derive(Clone, Copy, Default)]
+/// Rom header.
+///
+/// All fields are plain integers or byte arrays, so equality and hashing compare the full 64 bytes
+/// (reserved regions included) and two headers are equal only if they serialize identically.
+#[derive(Clone, Copy, Eq, Hash, PartialEq)]
 pub struct Header {
     // Magic number and PI registers.
     magic: Magic,
@@ -170,24 +178,57 @@ pub struct Header {
     _reserved_3: u8,
 }
 
+impl Default for Header {
+    /// All-zero header with a valid big-endian magic number, such that it may be written and read back.
+    fn default() -> Self {
+        Self {
+            magic: Magic::new(),
+            clock_rate: 0,
+            entry_point: 0,
+            release: 0,
+            crc1: 0,
+            crc2: 0,
+            _reserved_1: [0; 8],
+            name: [0; 20],
+            _reserved_2: [0; 7],
+            media: Media::default(),
+            _reserved_3: 0,
+        }
+    }
+}
+
 impl fmt::Display for Header {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         let name = self.name_str().unwrap_or("<???>").trim();
         let media_str = self.media.as_str().unwrap_or("????");
-        write!(formatter, "N64 ROM Header: {}\n", name)?;
-        write!(formatter, "  Checksums: (0x{:08X}, 0x{:08X})\n", self.crc1, self.crc2)?;
-        write!(formatter, "  Media Format: {}", media_str)
+        writeln!(formatter, "N64 ROM Header: {}", name)?;
+        writeln!(formatter, "  Checksums: (0x{:08X}, 0x{:08X})", self.crc1, self.crc2)?;
+        writeln!(formatter, "  Media Format: {}", media_str)?;
+        write!(formatter, "  Product Code: {}", self.product_code())
     }
 }
 
 impl Header {
     pub const SIZE: usize = 0x40;
+    /// Number of 32-bit words in a `Header`.
+    pub const WORDS: usize = Header::SIZE / 4;
 
     /// Get CRC values.
     pub fn crcs(&self) -> (u32, u32) {
         (self.crc1, self.crc2)
     }
 
+    /// Whether or not CRC values are set, as freshly assembled roms may have both values zeroed.
+    pub fn has_crc(&self) -> bool {
+        self.crc1 != 0 || self.crc2 != 0
+    }
+
+    /// Get cartridge ID (offsets `0x3C`-`0x3D`).
+    pub fn cartridge_id(&self) -> [u8; 2] {
+        let (_, id1, id2, _) = self.media.values();
+        [id1, id2]
+    }
+
     /// Get magic number field.
     pub fn magic(&self) -> &Magic {
         &self.magic
@@ -198,6 +239,20 @@ impl Header {
         &self.media
     }
 
+    /// Get media format character (offset `0x3B`), such as `N` for cartridge.
+    pub fn media_format(&self) -> char {
+        let (format, _, _, _) = self.media.chars();
+        format
+    }
+
+    /// Read from a slice of exactly `Header::SIZE` bytes in any byte order, along with the inferred `Endianness`.
+    pub fn from_slice(mut bytes: &[u8]) -> Result<(Self, Endianness), Error> {
+        if bytes.len() != Header::SIZE {
+            return Err(Error::InvalidSize(bytes.len()));
+        }
+        Self::read_ordered(&mut bytes)
+    }
+
     /// Get rom name as bytes.
     pub fn name(&self) -> &[u8; 20] {
         &self.name
@@ -213,7 +268,6 @@ impl Header {
         let mut header = Self::default();
         let (crc1, crc2) = ipl3.compute_crcs(program, fs);
         let name_bytes = &name.as_bytes()[..20];
-        header.magic = Magic::new();
         header.clock_rate = 15;
         header.entry_point = ipl3.offset(entry_point);
         header.release = 0;
@@ -224,6 +278,68 @@ impl Header {
         header
     }
 
+    /// Get product code as combination of media format, cartridge ID and region (such as `NZLE`).
+    pub fn product_code(&self) -> String {
+        let (format, id1, id2, region) = self.media.chars();
+        [format, id1, id2, region].iter().collect()
+    }
+
+    /// Get the libultra SDK version written by makerom (offsets `0x0E`-`0x0F`), if present.
+    ///
+    /// The high byte is the version number times 10 and the low byte is the revision letter, such as `0x144C` for
+    /// version 2.0L. Older roms leave this field zeroed, in which case `None` is returned.
+    pub fn sdk_version(&self) -> Option<u16> {
+        let version = self.release as u16;
+        let valid = self.release >> 16 == 0 && version >> 8 != 0 && (version as u8).is_ascii_uppercase();
+        if valid { Some(version) } else { None }
+    }
+
+    /// Get reserved words (offsets `0x18` and `0x1C`), read as big endian.
+    ///
+    /// Offsets `0x3C`-`0x3F` are not reserved, they hold the cartridge ID, region and version.
+    pub fn reserved_fields(&self) -> [u32; 2] {
+        let mut fields = [0; 2];
+        BigEndian::read_u32_into(&self._reserved_1, &mut fields);
+        fields
+    }
+
+    /// Set reserved words (offsets `0x18` and `0x1C`).
+    pub fn set_reserved_fields(&mut self, fields: [u32; 2]) {
+        BigEndian::write_u32_into(&fields, &mut self._reserved_1);
+    }
+
+    /// Serialize to big-endian bytes.
+    fn to_bytes(self) -> [u8; Header::SIZE] {
+        let mut bytes = [0; Header::SIZE];
+        // `write` writes exactly `Header::SIZE` bytes, so the buffer cannot run out.
+        self.write(&mut &mut bytes[..]).unwrap();
+        bytes
+    }
+
+    /// Get the 32-bit word at the given index (offset `index * 4`), for fields without a named accessor.
+    ///
+    /// Returns `None` if `index` is not less than `Header::WORDS`.
+    pub fn word(&self, index: usize) -> Option<u32> {
+        if index >= Header::WORDS {
+            return None;
+        }
+        let bytes = self.to_bytes();
+        Some(BigEndian::read_u32(&bytes[index * 4..(index + 1) * 4]))
+    }
+
+    /// Set the 32-bit word at the given index (offset `index * 4`), for fields without a named accessor.
+    ///
+    /// Fails with `Error::InvalidWordIndex` if `index` is not less than `Header::WORDS`.
+    pub fn set_word(&mut self, index: usize, value: u32) -> Result<(), Error> {
+        if index >= Header::WORDS {
+            return Err(Error::InvalidWordIndex(index));
+        }
+        let mut bytes = self.to_bytes();
+        BigEndian::write_u32(&mut bytes[index * 4..(index + 1) * 4], value);
+        *self = Self::read(&mut &bytes[..])?;
+        Ok(())
+    }
+
     /// Read ordered by converting to big endian.
     pub fn read_ordered<T: Read>(reader: &'_ mut T) -> Result<(Self, Endianness), Error> {
         let mut buf = [0; Header::SIZE];
@@ -270,3 +386,118 @@ impl Header {
         Ok(Header::SIZE)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn header_bytes() -> [u8; Header::SIZE] {
+        let mut buf = [0; Header::SIZE];
+        buf[..4].copy_from_slice(Magic::new().as_ref());
+        buf[0x20..0x34].copy_from_slice(b"THE LEGEND OF ZELDA ");
+        buf[0x3B..0x3F].copy_from_slice(b"NZLE");
+        buf
+    }
+
+    #[test]
+    fn header_eq_hash() {
+        let bytes = header_bytes();
+        let a = Header::read(&mut &bytes[..]).unwrap();
+        let b = Header::read(&mut &bytes[..]).unwrap();
+        let mut c = a;
+        c.crc1 = 0x1234_5678;
+
+        assert!(a == b);
+        assert!(a != c);
+        assert!(!a.has_crc());
+        assert!(c.has_crc());
+
+        let set: HashSet<Header> = [a, b, c].iter().cloned().collect();
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn header_product_code() {
+        let bytes = header_bytes();
+        let header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.media_format(), 'N');
+        assert_eq!(&header.cartridge_id(), b"ZL");
+        assert_eq!(header.product_code(), "NZLE");
+    }
+
+    #[test]
+    fn header_reserved_fields() {
+        let mut bytes = header_bytes();
+        bytes[0x18..0x20].copy_from_slice(&[0x12, 0x34, 0x56, 0x78, 0x9A, 0xBC, 0xDE, 0xF0]);
+        let mut header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.reserved_fields(), [0x1234_5678, 0x9ABC_DEF0]);
+
+        header.set_reserved_fields([0xDEAD_BEEF, 0]);
+        let mut output = Vec::new();
+        header.write(&mut output).unwrap();
+        assert_eq!(&output[0x18..0x20], &[0xDE, 0xAD, 0xBE, 0xEF, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn header_sdk_version() {
+        let mut bytes = header_bytes();
+        let header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.sdk_version(), None);
+        bytes[0x0C..0x10].copy_from_slice(&[0, 0, 0x14, b'L']);
+        let header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.sdk_version(), Some(0x144C));
+        bytes[0x0C] = 1;
+        let header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.sdk_version(), None);
+    }
+
+    #[test]
+    fn header_from_slice() {
+        let bytes = header_bytes();
+        let (header, order) = Header::from_slice(&bytes).unwrap();
+        assert_eq!(order, Endianness::Big);
+        assert_eq!(header.product_code(), "NZLE");
+
+        let mut little = bytes;
+        convert::convert(&mut little, Endianness::Big, Endianness::Little).unwrap();
+        let (converted, order) = Header::from_slice(&little).unwrap();
+        assert_eq!(order, Endianness::Little);
+        assert!(converted == header);
+
+        assert!(matches!(Header::from_slice(&bytes[..0x3C]), Err(Error::InvalidSize(0x3C))));
+    }
+
+    #[test]
+    fn header_default() {
+        let header = Header::default();
+        assert_eq!(header.magic().byte_order().unwrap(), Endianness::Big);
+        let mut output = Vec::new();
+        header.write(&mut output).unwrap();
+        let (reread, order) = Header::from_slice(&output).unwrap();
+        assert_eq!(order, Endianness::Big);
+        assert!(reread == header);
+    }
+
+    #[test]
+    fn header_words() {
+        let bytes = header_bytes();
+        let mut header = Header::read(&mut &bytes[..]).unwrap();
+        assert_eq!(header.word(0), Some(header.magic().to_u32()));
+        assert_eq!(header.word(8), Some(u32::from_be_bytes(*b"THE ")));
+        header.set_word(2, 0x8000_0400).unwrap();
+        assert_eq!(header.word(2), Some(0x8000_0400));
+        assert_eq!(header.product_code(), "NZLE");
+        // Words may span multiple fields, such as the end of the media format and the last reserved byte.
+        header.set_word(15, 0x5A4C_45FF).unwrap();
+        assert_eq!(header.product_code(), "NZLE");
+        let mut output = Vec::new();
+        header.write(&mut output).unwrap();
+        assert_eq!(&output[0x08..0x0C], &[0x80, 0x00, 0x04, 0x00]);
+        assert_eq!(&output[0x3C..0x40], b"ZLE\xFF");
+
+        assert_eq!(header.word(Header::WORDS), None);
+        assert!(matches!(header.set_word(Header::WORDS, 0), Err(Error::InvalidWordIndex(16))));
+        assert_eq!(header.word(15), Some(0x5A4C_45FF));
+    }
+}
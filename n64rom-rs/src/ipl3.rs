@@ -17,92 +17,70 @@ pub enum Error {
     IOError(#[from] io::Error),
     #[error("Unable to read IPL3: Expected file size {}, found {0}", IPL_SIZE)]
     FileSizeError(u64),
+    #[error("Unable to read IPL3: Truncated after {0} bytes, expected {1}")]
+    TruncatedIPL3(usize, usize),
 }
 
-#[derive(Clone, Copy)]
-/// IPL3 definitions.
-pub enum IPL3 {
-    Cic6101([u8; IPL_SIZE]),
-    Cic6102([u8; IPL_SIZE]),
-    Cic6103([u8; IPL_SIZE]),
-    Cic6105([u8; IPL_SIZE]),
-    Cic6106([u8; IPL_SIZE]),
-    Cic7102([u8; IPL_SIZE]),
-    Unknown([u8; IPL_SIZE]),
+/// CIC boot chip variants, each expecting CRC values computed with their own seed.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum CIC {
+    Cic6101,
+    Cic6102,
+    Cic6103,
+    Cic6105,
+    Cic6106,
+    Cic7102,
 }
 
-impl fmt::Display for IPL3 {
+impl fmt::Display for CIC {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
-            Self::Cic6101(_) => "CIC-NUS-6101",
-            Self::Cic6102(_) => "CIC-NUS-6102",
-            Self::Cic6103(_) => "CIC-NUS-6103",
-            Self::Cic6105(_) => "CIC-NUS-6105",
-            Self::Cic6106(_) => "CIC-NUS-6106",
-            Self::Cic7102(_) => "CIC-NUS-7102",
-            Self::Unknown(_) => "Unknown",
+            Self::Cic6101 => "CIC-NUS-6101",
+            Self::Cic6102 => "CIC-NUS-6102",
+            Self::Cic6103 => "CIC-NUS-6103",
+            Self::Cic6105 => "CIC-NUS-6105",
+            Self::Cic6106 => "CIC-NUS-6106",
+            Self::Cic7102 => "CIC-NUS-7102",
         };
         write!(f, "{}", s)
     }
 }
 
-impl fmt::Debug for IPL3 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        <Self as fmt::Display>::fmt(self, f)
-    }
-}
+impl CIC {
+    /// All `CIC` variants.
+    const ALL: [CIC; 6] = [Self::Cic6101, Self::Cic6102, Self::Cic6103, Self::Cic6105, Self::Cic6106, Self::Cic7102];
 
-impl IPL3 {
-    pub fn read<T: Read>(reader: &mut T) -> io::Result<Self> {
-        // Read file contents
-        let mut ipl = [0; IPL_SIZE];
-        reader.read_exact(&mut ipl)?;
-
-        // Check for known IPLs
-        let mut hasher = Hasher::new();
-        hasher.update(&ipl);
-        let ipl3 = match hasher.finalize() {
-            0x6170_a4a1 => Self::Cic6101(ipl),
-            0x90bb_6cb5 => Self::Cic6102(ipl),
-            0x0b05_0ee0 => Self::Cic6103(ipl),
-            0x98bc_2c86 => Self::Cic6105(ipl),
-            0xacc8_580a => Self::Cic6106(ipl),
-            0x009e_9ea3 => Self::Cic7102(ipl),
-            _ => Self::Unknown(ipl),
-        };
-
-        Ok(ipl3)
+    /// Iterate over all `CIC` variants.
+    pub fn all() -> impl Iterator<Item = CIC> {
+        Self::ALL.iter().copied()
     }
 
-    pub fn read_path(path: impl AsRef<Path>) -> Result<Self, Error> {
-        // TODO
-        let mut f = File::open(path)?;
-
-        // Check the file size
-        let metadata = f.metadata()?;
-        let len = metadata.len();
-        if len as usize != IPL_SIZE {
-            return Err(Error::FileSizeError(len))
+    /// Get human-readable boot chip name, including the PAL chip sharing the same IPL3 if any.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Cic6101 => "CIC-6101",
+            Self::Cic6102 => "CIC-6102/7101",
+            Self::Cic6103 => "CIC-6103/7103",
+            Self::Cic6105 => "CIC-6105/7105",
+            Self::Cic6106 => "CIC-6106/7106",
+            Self::Cic7102 => "CIC-7102",
         }
-
-        let ipl3 = Self::read(&mut f)?;
-
-        Ok(ipl3)
     }
 
-    pub fn get_ipl(&self) -> &[u8; IPL_SIZE] {
+    /// Initial checksum value used when computing CRC values.
+    pub fn seed(self) -> u32 {
         match self {
-            Self::Cic6101(bin) => bin,
-            Self::Cic6102(bin) => bin,
-            Self::Cic6103(bin) => bin,
-            Self::Cic6105(bin) => bin,
-            Self::Cic6106(bin) => bin,
-            Self::Cic7102(bin) => bin,
-            Self::Unknown(bin) => bin,
+            Self::Cic6103 => 0xa388_6759,
+            Self::Cic6105 => 0xdf26_f436,
+            Self::Cic6106 => 0x1fea_617a,
+            _ => 0xf8ca_4ddc,
         }
     }
 
-    pub fn compute_crcs(&self, program: &[u8], fs: &[u8]) -> (u32, u32) {
+    /// Compute CRC values using the seed and algorithm of this `CIC`.
+    ///
+    /// The IPL3 data is only used by `CIC::Cic6105`, which reads a table hidden in its bootcode.
+    pub fn compute_crcs(self, ipl: &[u8; IPL_SIZE], program: &[u8], fs: &[u8]) -> (u32, u32) {
         let padding_length = (2 - (program.len() & 1)) & 1;
         let padding = [0; 1];
         let program = program
@@ -115,15 +93,10 @@ impl IPL3 {
             .chunks(4);
 
         // Initial checksum value
-        let checksum = match self {
-            Self::Cic6103(_) => 0xa388_6759,
-            Self::Cic6105(_) => 0xdf26_f436,
-            Self::Cic6106(_) => 0x1fea_617a,
-            _ => 0xf8ca_4ddc,
-        };
+        let checksum = self.seed();
 
         // NUS-IPL3-6105 has a special 64-word table hidden in the IPL
-        let mut ipl = self.get_ipl().chunks(4).skip(452).take(64).cycle();
+        let mut ipl = ipl.chunks(4).skip(452).take(64).cycle();
 
         // Six accumulators
         let mut acc1 = Wrapping(checksum);
@@ -166,9 +139,9 @@ impl IPL3 {
 
             // Advance accumulator 6
             match self {
-                Self::Cic6105(_) => {
+                Self::Cic6105 => {
                     let current_ipl = ipl.next().unwrap();
-                    let current_ipl = Wrapping(BigEndian::read_u32(&current_ipl));
+                    let current_ipl = Wrapping(BigEndian::read_u32(current_ipl));
                     acc6 += current ^ current_ipl;
                 }
                 _ => {
@@ -178,13 +151,143 @@ impl IPL3 {
         }
 
         let (crc1, crc2) = match self {
-            Self::Cic6103(_) => ((acc1 ^ acc2) + acc3, (acc4 ^ acc5) + acc6),
-            Self::Cic6106(_) => (acc1 * acc2 + acc3, acc4 * acc5 + acc6),
+            Self::Cic6103 => ((acc1 ^ acc2) + acc3, (acc4 ^ acc5) + acc6),
+            Self::Cic6106 => (acc1 * acc2 + acc3, acc4 * acc5 + acc6),
             _ => (acc1 ^ acc2 ^ acc3, acc4 ^ acc5 ^ acc6),
         };
 
         (crc1.0, crc2.0)
     }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+/// IPL3 definitions.
+pub enum IPL3 {
+    Cic6101([u8; IPL_SIZE]),
+    Cic6102([u8; IPL_SIZE]),
+    Cic6103([u8; IPL_SIZE]),
+    Cic6105([u8; IPL_SIZE]),
+    Cic6106([u8; IPL_SIZE]),
+    Cic7102([u8; IPL_SIZE]),
+    Unknown([u8; IPL_SIZE]),
+}
+
+impl fmt::Display for IPL3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cic() {
+            Some(cic) => write!(f, "{}", cic),
+            None => write!(f, "Unknown"),
+        }
+    }
+}
+
+impl fmt::Debug for IPL3 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        <Self as fmt::Display>::fmt(self, f)
+    }
+}
+
+impl IPL3 {
+    pub fn read<T: Read>(reader: &mut T) -> Result<Self, Error> {
+        // Read file contents, same as `read_exact` but keeping count for error reporting
+        let mut ipl = [0; IPL_SIZE];
+        let mut amount = 0;
+        while amount < IPL_SIZE {
+            match reader.read(&mut ipl[amount..]) {
+                Ok(0) => return Err(Error::TruncatedIPL3(amount, IPL_SIZE)),
+                Ok(length) => amount += length,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(Self::from_bytes(ipl))
+    }
+
+    /// Create `IPL3` from bootcode, identifying known IPLs by their CRC32.
+    ///
+    /// Patched bootcode will not be identified, see `from_cic` for tagging it with the `CIC` it targets.
+    pub fn from_bytes(ipl: [u8; IPL_SIZE]) -> Self {
+        let mut hasher = Hasher::new();
+        hasher.update(&ipl);
+        match hasher.finalize() {
+            0x6170_a4a1 => Self::Cic6101(ipl),
+            0x90bb_6cb5 => Self::Cic6102(ipl),
+            0x0b05_0ee0 => Self::Cic6103(ipl),
+            0x98bc_2c86 => Self::Cic6105(ipl),
+            0xacc8_580a => Self::Cic6106(ipl),
+            0x009e_9ea3 => Self::Cic7102(ipl),
+            _ => Self::Unknown(ipl),
+        }
+    }
+
+    /// Create `IPL3` from bootcode for the given `CIC`, without checking that the bootcode is a known IPL.
+    ///
+    /// Header CRC values are computed using the seed and algorithm of the `CIC`, so that CRC values for patched
+    /// bootcode can be computed. Note the CIC chip also verifies a checksum of the bootcode itself, which is fixed
+    /// per chip and not recomputed here.
+    pub fn from_cic(cic: CIC, ipl: [u8; IPL_SIZE]) -> Self {
+        match cic {
+            CIC::Cic6101 => Self::Cic6101(ipl),
+            CIC::Cic6102 => Self::Cic6102(ipl),
+            CIC::Cic6103 => Self::Cic6103(ipl),
+            CIC::Cic6105 => Self::Cic6105(ipl),
+            CIC::Cic6106 => Self::Cic6106(ipl),
+            CIC::Cic7102 => Self::Cic7102(ipl),
+        }
+    }
+
+    pub fn read_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        // TODO
+        let mut f = File::open(path)?;
+
+        // Check the file size
+        let metadata = f.metadata()?;
+        let len = metadata.len();
+        if len as usize != IPL_SIZE {
+            return Err(Error::FileSizeError(len))
+        }
+
+        let ipl3 = Self::read(&mut f)?;
+
+        Ok(ipl3)
+    }
+
+    pub fn get_ipl(&self) -> &[u8; IPL_SIZE] {
+        match self {
+            Self::Cic6101(bin) => bin,
+            Self::Cic6102(bin) => bin,
+            Self::Cic6103(bin) => bin,
+            Self::Cic6105(bin) => bin,
+            Self::Cic6106(bin) => bin,
+            Self::Cic7102(bin) => bin,
+            Self::Unknown(bin) => bin,
+        }
+    }
+
+    /// Get the `CIC` of a known IPL3.
+    pub fn cic(&self) -> Option<CIC> {
+        match self {
+            Self::Cic6101(_) => Some(CIC::Cic6101),
+            Self::Cic6102(_) => Some(CIC::Cic6102),
+            Self::Cic6103(_) => Some(CIC::Cic6103),
+            Self::Cic6105(_) => Some(CIC::Cic6105),
+            Self::Cic6106(_) => Some(CIC::Cic6106),
+            Self::Cic7102(_) => Some(CIC::Cic7102),
+            Self::Unknown(_) => None,
+        }
+    }
+
+    /// Whether or not the bootcode bytes of both IPL3s are identical, regardless of the `CIC` they are tagged with.
+    pub fn eq_bootcode(&self, other: &IPL3) -> bool {
+        self.get_ipl() == other.get_ipl()
+    }
+
+    /// Compute CRC values for this IPL3. An unknown IPL3 is treated as `CIC::Cic6102`.
+    pub fn compute_crcs(&self, program: &[u8], fs: &[u8]) -> (u32, u32) {
+        let cic = self.cic().unwrap_or(CIC::Cic6102);
+        cic.compute_crcs(self.get_ipl(), program, fs)
+    }
 
     /// Offset the entry point for the current IPL3
     pub fn offset(&self, entry_point: u32) -> u32 {
@@ -206,6 +309,35 @@ impl IPL3 {
 mod tests {
     use super::*;
 
+    #[test]
+    fn from_cic() {
+        let mut ipl = [0; IPL_SIZE];
+        ipl[0x100] = 0x42;
+        assert_eq!(IPL3::from_bytes(ipl).cic(), None);
+        let ipl3 = IPL3::from_cic(CIC::Cic6105, ipl);
+        assert_eq!(ipl3.cic(), Some(CIC::Cic6105));
+        assert_eq!(ipl3.get_ipl(), &ipl);
+        let program = [0x11; 0x100];
+        assert_eq!(ipl3.compute_crcs(&program, &[]), CIC::Cic6105.compute_crcs(&ipl, &program, &[]));
+    }
+
+    #[test]
+    fn eq_bootcode() {
+        let mut ipl = [0; IPL_SIZE];
+        ipl[0x100] = 0x42;
+        let ipl3 = IPL3::from_bytes(ipl);
+        assert!(ipl3.eq_bootcode(&IPL3::from_cic(CIC::Cic6105, ipl)));
+        assert_ne!(ipl3, IPL3::from_cic(CIC::Cic6105, ipl));
+        ipl[0x100] = 0x43;
+        assert!(!ipl3.eq_bootcode(&IPL3::from_bytes(ipl)));
+    }
+
+    #[test]
+    fn cic_names() {
+        let names: Vec<&str> = CIC::all().map(|cic| cic.name()).collect();
+        assert_eq!(names, vec!["CIC-6101", "CIC-6102/7101", "CIC-6103/7103", "CIC-6105/7105", "CIC-6106/7106", "CIC-7102"]);
+    }
+
     #[test]
     fn crc_ipl3_6101() {
         let ipl3 = IPL3::Cic6101([0; IPL_SIZE]);
@@ -272,6 +404,13 @@ mod tests {
         assert_eq!(crc2, 0xb2de_a121);
     }
 
+    #[test]
+    fn read_truncated() {
+        let data = [0; IPL_SIZE - 0x10];
+        let result = IPL3::read(&mut &data[..]);
+        assert!(matches!(result, Err(Error::TruncatedIPL3(read, IPL_SIZE)) if read == IPL_SIZE - 0x10));
+    }
+
     #[test]
     fn offset_ipl3_6101() {
         let ipl3 = IPL3::Cic6101([0; IPL_SIZE]);
@@ -1,6 +1,5 @@
 #![deny(clippy::all)]
 #![allow(clippy::field_reassign_with_default)]
-#![feature(box_syntax)]
 #![feature(wrapping_int_impl)]
 #![warn(rust_2018_idioms)]
 
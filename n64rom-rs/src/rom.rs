@@ -1,15 +1,19 @@
 use std::fmt;
-use std::io::{self, Cursor, Read, Write};
+use std::io::{self, BufReader, Cursor, Read, Write};
+use std::str::FromStr;
 use thiserror::Error;
 
-use crate::header::Header;
-use crate::ipl3::{IPL3, IPL_SIZE};
+use crate::header::{Header, Magic};
+use crate::ipl3::{CIC, IPL3, IPL_SIZE};
 use crate::stream::{Reader, Writer};
 use crate::util::{FileSize, MEBIBYTE};
 
 /// Total size of rom header and IPL3. This will be the file offset where data begins.
 pub const HEAD_SIZE: usize = Header::SIZE + IPL_SIZE;
 
+/// Size of the header some copier devices prepend to rom dumps.
+pub const COPIER_HEADER_SIZE: usize = 0x200;
+
 /// Maximum expected rom size (64 MiB).
 pub const MAX_SIZE: usize = 1024 * 1024 * 64;
 
@@ -19,10 +23,19 @@ pub enum Error {
     IOError(#[from] io::Error),
     #[error("{0}")]
     HeaderError(#[from] crate::header::Error),
+    #[error("{0}")]
+    IPL3Error(#[from] crate::ipl3::Error),
+    #[error("Unknown byte order name: {0}")]
+    UnknownEndianness(String),
+    #[error("Invalid head size: 0x{0:X}, expected 0x{HEAD_SIZE:X}")]
+    InvalidHeadSize(usize),
     #[error("Unsupported endianness for this operation: {0}")]
     UnsupportedEndianness(Endianness),
 }
 
+/// Buffer size used by `Rom::read_buffered` when reading all data.
+const BUFFERED_SIZE: usize = 1024 * 64;
+
 #[derive(Clone, Copy, Debug, PartialEq)]
 /// Convenience wrapper enum around the separate Swap endianness enums.
 pub enum Endianness {
@@ -41,6 +54,26 @@ impl Endianness {
     }
 }
 
+impl From<FileExt> for Endianness {
+    fn from(ext: FileExt) -> Self {
+        Self::from_file_ext(ext)
+    }
+}
+
+impl FromStr for Endianness {
+    type Err = Error;
+
+    /// Parse from a byte order name: `big`, `little` or `mixed`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "big" => Ok(Self::Big),
+            "little" => Ok(Self::Little),
+            "mixed" => Ok(Self::Mixed),
+            _ => Err(Error::UnknownEndianness(s.to_string())),
+        }
+    }
+}
+
 impl fmt::Display for Endianness {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -83,6 +116,29 @@ impl fmt::Display for FileExt {
     }
 }
 
+/// Stored and computed CRC values of a `Rom`, see `Rom::crc_report`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct CrcReport {
+    /// CRC values stored in the `Header`.
+    pub stored: (u32, u32),
+    /// CRC values computed from `Rom` data.
+    pub computed: (u32, u32),
+    /// Whether or not the stored values match the computed values.
+    pub matches: bool,
+}
+
+impl fmt::Display for CrcReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (stored, computed) = (self.stored, self.computed);
+        if self.matches {
+            write!(f, "Correct CRC values: ({:#010X}, {:#010X})", stored.0, stored.1)
+        } else {
+            write!(f, "Bad CRC values, expected: ({:#010X}, {:#010X}), found: ({:#010X}, {:#010X})",
+                computed.0, computed.1, stored.0, stored.1)
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Rom {
     pub header: Header,
@@ -91,22 +147,26 @@ pub struct Rom {
     pub image: Vec<u8>,
     /// Byte order (endianness) of rom file.
     order: Endianness,
+    /// `Header` and `IPL3` as last read from or flushed to the image, if known to be in sync.
+    synced: Option<(Header, IPL3)>,
+    /// Copier header which preceded the rom data when read, if any.
+    copier_header: Option<Vec<u8>>,
 }
 
 impl fmt::Display for Rom {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(formatter, "{}", self.header)?;
-        write!(formatter, "  IPL3: {}", self.ipl3)?;
+        writeln!(formatter, "{}", self.header)?;
+        writeln!(formatter, "  IPL3: {}", self.ipl3)?;
         write!(formatter, "  Byte Order: {}", self.order)?;
         // Only show rom size if we have data.
         if self.image.len() > HEAD_SIZE {
             let filesize = FileSize::from(self.len() as u64, MEBIBYTE);
             match filesize {
                 FileSize::Float(value) => {
-                    write!(formatter, "  Rom Size: {:.*} MiB", 1, value)?;
+                    write!(formatter, "\n  Rom Size: {:.*} MiB", 1, value)?;
                 }
                 FileSize::Int(value) => {
-                    write!(formatter, "  Rom Size: {} MiB", value)?;
+                    write!(formatter, "\n  Rom Size: {} MiB", value)?;
                 }
             }
         }
@@ -123,6 +183,12 @@ impl Rom {
         (result, calc)
     }
 
+    /// Calculate CRC values from `Rom` data and report them along with the CRC values in the `Header`.
+    pub fn crc_report(&self) -> CrcReport {
+        let (matches, computed) = self.check_crc();
+        CrcReport { stored: self.header.crcs(), computed, matches }
+    }
+
     /// Correct the CRC values in the header.
     pub fn correct_crc(&mut self) -> bool {
         let (result, (calc1, calc2)) = self.check_crc();
@@ -138,6 +204,48 @@ impl Rom {
         }
     }
 
+    /// Make the `Rom` ready for writing: correct the CRC values in the header, then flush `Header` and `IPL3` to the
+    /// image.
+    ///
+    /// Fails with `Error::InvalidHeadSize` if the image is too small to contain the head. CRC values of a rom with an
+    /// unknown `IPL3` are computed as for `CIC::Cic6102`.
+    pub fn finalize(&mut self) -> Result<(), Error> {
+        if self.image.len() < HEAD_SIZE {
+            return Err(Error::InvalidHeadSize(self.image.len()));
+        }
+        self.correct_crc();
+        self.flush()?;
+        Ok(())
+    }
+
+    /// Replace the `IPL3` and recompute the header CRC values for it, returning the new CRC values.
+    ///
+    /// Patched bootcode should be created with `IPL3::from_cic`, otherwise CRC values are computed as for
+    /// `CIC::Cic6102`.
+    pub fn set_ipl3(&mut self, ipl3: IPL3) -> (u32, u32) {
+        self.ipl3 = ipl3;
+        self.correct_crc();
+        self.header.crcs()
+    }
+
+    /// Whether or not the rom would pass the boot checks of hardware: a valid magic number, a known `IPL3` and header
+    /// CRC values matching those computed with the seed of its `CIC`.
+    ///
+    /// The bootcode checksum verified by the PIF is not checked, see `IPL3::from_cic`.
+    pub fn is_bootable(&self) -> bool {
+        self.header.magic().byte_order().is_ok() && self.ipl3.cic().is_some() && self.check_crc().0
+    }
+
+    /// Detect which `CIC` the header CRC values were computed for, by recomputing them with each seed.
+    ///
+    /// CIC-NUS-6101, 6102 and 7102 share a seed, so `CIC::Cic6102` is returned for any of them.
+    pub fn detect_cic_by_crc(&self) -> Option<CIC> {
+        const CANDIDATES: [CIC; 4] = [CIC::Cic6102, CIC::Cic6103, CIC::Cic6105, CIC::Cic6106];
+        let crcs = self.header.crcs();
+        let ipl = self.ipl3.get_ipl();
+        CANDIDATES.iter().copied().find(|cic| cic.compute_crcs(ipl, self.data(), &[]) == crcs)
+    }
+
     /// Get slice of `Rom` image data, not including header or `IPL3`.
     pub fn data(&self) -> &[u8] {
         &self.image[HEAD_SIZE..]
@@ -149,25 +257,92 @@ impl Rom {
     }
 
     /// Create `Rom` from a raw image without copying. Requires image data to be in big-endian format.
-    pub fn from_image(image: Vec<u8>) -> Result<Self, Error> {
+    ///
+    /// A copier header preceding the rom data is detected and removed, which requires moving the image data.
+    pub fn from_image(mut image: Vec<u8>) -> Result<Self, Error> {
+        let copier_header = match Self::has_copier_header(&image) {
+            true => Some(image.drain(..COPIER_HEADER_SIZE).collect()),
+            false => None,
+        };
         let mut head = &image[..HEAD_SIZE];
         // Read header & infer endianness.
         let (header, order) = Header::read_ordered(&mut head)?;
         if order == Endianness::Big {
             let ipl3 = IPL3::read(&mut head)?;
-            Ok(Rom::from(header, ipl3, image, order))
+            let mut rom = Rom::from(header, ipl3, image, order);
+            rom.synced = Some((header, ipl3));
+            rom.copier_header = copier_header;
+            Ok(rom)
         } else {
             Err(Error::UnsupportedEndianness(order))
         }
     }
 
+    /// Whether or not data begins with a copier header: no known magic at the start, but a known magic following it.
+    fn has_copier_header(data: &[u8]) -> bool {
+        let magic_at = |offset: usize| {
+            data.get(offset..offset + Magic::SIZE).is_some_and(|bytes| Magic::infer_byte_order(bytes).is_ok())
+        };
+        !magic_at(0) && magic_at(COPIER_HEADER_SIZE)
+    }
+
+    /// Read the first `Header::SIZE` bytes of rom data, skipping a copier header if present.
+    ///
+    /// Only the bytes needed are read, so that no rom data is consumed past the header.
+    fn read_start<T: Read>(reader: &mut T) -> Result<(Option<Vec<u8>>, [u8; Header::SIZE]), Error> {
+        let mut start = [0; Header::SIZE];
+        reader.read_exact(&mut start)?;
+        if let Err(err) = Magic::infer_byte_order(&start) {
+            // Rom data may follow a copier header, otherwise report the original magic.
+            let mut copier_header = vec![0; COPIER_HEADER_SIZE];
+            copier_header[..Header::SIZE].copy_from_slice(&start);
+            // Data too short to hold a copier header is not a rom either.
+            let result = reader.read_exact(&mut copier_header[Header::SIZE..])
+                .and_then(|_| reader.read_exact(&mut start));
+            match result {
+                Err(eof) if eof.kind() == io::ErrorKind::UnexpectedEof => return Err(err.into()),
+                result => result?,
+            }
+            if Magic::infer_byte_order(&start).is_err() {
+                return Err(err.into());
+            }
+            return Ok((Some(copier_header), start));
+        }
+        Ok((None, start))
+    }
+
+    /// Whether or not a copier header preceded the rom data when read. Copier headers are not written.
+    pub fn had_copier_header(&self) -> bool {
+        self.copier_header.is_some()
+    }
+
+    /// Get the copier header which preceded the rom data when read, if any.
+    pub fn copier_header(&self) -> Option<&[u8]> {
+        self.copier_header.as_deref()
+    }
+
+    /// Create `Rom` from a head (header & `IPL3`) and body data. Requires the head to be in big-endian format.
+    pub fn from_parts(head: &[u8], body: Vec<u8>) -> Result<Self, Error> {
+        if head.len() != HEAD_SIZE {
+            return Err(Error::InvalidHeadSize(head.len()));
+        }
+        let mut image = Vec::with_capacity(HEAD_SIZE + body.len());
+        image.extend_from_slice(head);
+        image.extend(body);
+        Self::from_image(image)
+    }
+
     /// Create `Rom` from fields.
+    ///
+    /// The image is not assumed to contain the given `Header` and `IPL3`, so the next `flush` will write them.
     pub fn from(header: Header, ipl3: IPL3, image: Vec<u8>, order: Endianness) -> Self {
         Self {
             header,
             ipl3,
             image,
             order,
+            synced: None,
+            copier_header: None,
         }
     }
 
@@ -177,24 +352,97 @@ impl Rom {
     }
 
     /// Get slice of full `Rom` image data as mutable.
+    ///
+    /// Changes to the head are kept when flushing, unless `header` or `ipl3` are changed as well.
     pub fn full_mut(&mut self) -> &mut [u8] {
         &mut self.image[..]
     }
 
+    /// Compute the MD5 hash of the full `Rom` image, in big-endian byte order.
+    #[cfg(feature = "hashes")]
+    pub fn md5(&self) -> [u8; 16] {
+        use md5::{Digest, Md5};
+        Md5::digest(self.full()).into()
+    }
+
+    /// Compute the SHA-1 hash of the full `Rom` image, in big-endian byte order.
+    #[cfg(feature = "hashes")]
+    pub fn sha1(&self) -> [u8; 20] {
+        use sha1::{Digest, Sha1};
+        Sha1::digest(self.full()).into()
+    }
+
     /// Get the `Endianness` of the parsed `Rom` data.
     pub fn order(&self) -> Endianness {
         self.order
     }
 
     /// Read `Rom` with all data.
-    pub fn read<T: Read>(mut reader: &mut T) -> Result<Self, crate::header::Error> {
+    pub fn read<T: Read>(mut reader: &mut T) -> Result<Self, Error> {
         Self::read_with_body(&mut reader, true)
     }
 
-    /// Read `Rom`.
-    pub fn read_with_body<T: Read>(mut reader: &mut T, read_body: bool) -> Result<Self, crate::header::Error> {
+    /// Read `Rom` with all data, failing with `Error::UnsupportedEndianness` if it is not in the expected byte order.
+    ///
+    /// The byte order is checked before reading any data past the header.
+    pub fn read_as<T: Read>(reader: &mut T, expected: Endianness) -> Result<Self, Error> {
+        let start = Self::read_start(reader)?;
+        let order = Magic::infer_byte_order(&start.1)?;
+        if order != expected {
+            return Err(Error::UnsupportedEndianness(order));
+        }
+        Self::read_from_start(reader, start, usize::MAX)
+    }
+
+    /// Read `Rom` through a `BufReader`, for efficiently reading from slow streams such as stdin or sockets.
+    ///
+    /// If `read_body` is not set, exactly `HEAD_SIZE` bytes (plus `COPIER_HEADER_SIZE` if a copier header is present)
+    /// are consumed from the reader, so the stream is left positioned at the start of the body.
+    pub fn read_buffered<R: Read>(mut reader: R, read_body: bool) -> Result<Self, Error> {
+        if read_body {
+            let mut reader = BufReader::with_capacity(BUFFERED_SIZE, reader);
+            Self::read_with_body(&mut reader, true)
+        } else {
+            let start = Self::read_start(&mut reader)?;
+            // Limit underlying reads so the buffer cannot read ahead past the head.
+            let rest = reader.take((HEAD_SIZE - Header::SIZE) as u64);
+            let mut reader = BufReader::with_capacity(HEAD_SIZE, rest);
+            Self::read_from_start(&mut reader, start, 0)
+        }
+    }
+
+    /// Read `Rom`, with all data if `read_body` is set or only the head otherwise.
+    pub fn read_with_body<T: Read>(reader: &mut T, read_body: bool) -> Result<Self, Error> {
+        let limit = if read_body { usize::MAX } else { 0 };
+        Self::read_with_limit(reader, limit)
+    }
+
+    /// Read `Rom` with all data, along with the total amount of bytes consumed from the reader.
+    ///
+    /// The count includes any copier header, so it can be compared against an expected file size to detect a truncated
+    /// dump, which may otherwise be mistaken for a smaller rom.
+    pub fn read_counting<T: Read>(reader: &mut T) -> Result<(Self, usize), Error> {
+        let rom = Self::read_with_limit(reader, usize::MAX)?;
+        let count = rom.copier_header.as_ref().map_or(0, Vec::len) + rom.len();
+        Ok((rom, count))
+    }
+
+    /// Read `Rom` head and up to `limit` bytes of data following it.
+    ///
+    /// A copier header preceding the rom data is skipped, see `had_copier_header`.
+    pub fn read_with_limit<T: Read>(reader: &mut T, limit: usize) -> Result<Self, Error> {
+        let start = Self::read_start(reader)?;
+        Self::read_from_start(reader, start, limit)
+    }
+
+    /// Read `Rom` from the rest of the data following the bytes read by `read_start`.
+    fn read_from_start<T: Read>(
+        mut reader: &mut T,
+        (copier_header, start): (Option<Vec<u8>>, [u8; Header::SIZE]),
+        limit: usize,
+    ) -> Result<Self, Error> {
         // Read header & infer endianness
-        let (header, order) = Header::read_ordered(&mut reader)?;
+        let (header, order) = Header::from_slice(&start)?;
 
         // Create new reader based on endianness, read remaining with it
         let mut reader = Reader::from(&mut reader, order);
@@ -204,10 +452,8 @@ impl Rom {
         let mut image = Vec::new();
         header.write(&mut image)?;
         image.extend(ipl3.get_ipl());
-        // Read remaining data if specified.
-        if read_body {
-            reader.read_to_end(&mut image)?;
-        }
+        // Read remaining data up to the limit.
+        reader.take(limit as u64).read_to_end(&mut image)?;
         let image = image;
 
         let rom = Self {
@@ -215,17 +461,27 @@ impl Rom {
             ipl3,
             image,
             order,
+            synced: Some((header, ipl3)),
+            copier_header,
         };
 
         Ok(rom)
     }
 
     /// Flush `Header` and `IPL3` to underlying buffer.
+    ///
+    /// Does nothing if neither has changed since last read or flushed, so direct changes to the head of the image are
+    /// not overwritten with stale values. Returns the amount of bytes written.
     pub fn flush(&mut self) -> io::Result<usize> {
+        let current = (self.header, self.ipl3);
+        if self.synced == Some(current) {
+            return Ok(0);
+        }
         let slice = &mut self.image[..HEAD_SIZE];
         let mut cursor = Cursor::new(slice);
         let mut written = self.header.write(&mut cursor)?;
         written += self.ipl3.write(&mut cursor)?;
+        self.synced = Some(current);
         Ok(written)
     }
 
@@ -242,6 +498,15 @@ impl Rom {
         self.write_raw(writer, endianness)
     }
 
+    /// Write `Rom` data to writer after flushing, returning the amount of bytes written and the byte order used.
+    ///
+    /// The byte order is `endianness` if given, otherwise the order the `Rom` was read in.
+    pub fn write_ordered<T: Write>(&mut self, writer: &mut T, endianness: Option<Endianness>) -> io::Result<(usize, Endianness)> {
+        let order = endianness.unwrap_or(self.order);
+        let written = self.write(writer, Some(order))?;
+        Ok((written, order))
+    }
+
     /// Get full length of `Rom` data.
     pub fn len(&self) -> usize {
         self.image.len()
@@ -251,4 +516,288 @@ impl Rom {
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
+
+    /// Shorten `Rom` data to the given length, which is never less than the head.
+    pub fn truncate(&mut self, len: usize) {
+        self.image.truncate(len.max(HEAD_SIZE));
+    }
+
+    /// Extend `Rom` data with `fill` bytes to a multiple of `align`, returning the new length.
+    ///
+    /// Panics if `align` is zero.
+    pub fn pad_to_alignment(&mut self, align: usize, fill: u8) -> usize {
+        let len = self.image.len().next_multiple_of(align);
+        self.image.resize(len, fill);
+        len
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::header::Magic;
+
+    fn test_image() -> Vec<u8> {
+        let mut image = vec![0; HEAD_SIZE + 0x100];
+        image[..4].copy_from_slice(Magic::new().as_ref());
+        image
+    }
+
+    #[test]
+    fn flush_keeps_head_changes() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        rom.full_mut()[0x20..0x24].copy_from_slice(b"TEST");
+        let mut output = Vec::new();
+        rom.write(&mut output, None).unwrap();
+        assert_eq!(&output[0x20..0x24], b"TEST");
+    }
+
+    #[test]
+    fn write_ordered() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        let mut output = Vec::new();
+        assert_eq!(rom.write_ordered(&mut output, None).unwrap(), (output.len(), Endianness::Big));
+        assert_eq!(output, rom.full());
+        let mut output = Vec::new();
+        let (written, order) = rom.write_ordered(&mut output, Some(Endianness::Mixed)).unwrap();
+        assert_eq!((written, order), (rom.len(), Endianness::Mixed));
+        assert_eq!(&output[..4], &[0x37, 0x80, 0x40, 0x12]);
+    }
+
+    #[test]
+    fn flush_writes_header_changes() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        assert_eq!(rom.flush().unwrap(), 0);
+        rom.correct_crc();
+        assert_eq!(rom.flush().unwrap(), HEAD_SIZE);
+        let (crc1, crc2) = rom.header.crcs();
+        assert_eq!(&rom.full()[0x10..0x14], &crc1.to_be_bytes());
+        assert_eq!(&rom.full()[0x14..0x18], &crc2.to_be_bytes());
+        assert_eq!(rom.flush().unwrap(), 0);
+    }
+
+    #[test]
+    fn detect_cic_by_crc() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        assert_eq!(rom.detect_cic_by_crc(), None);
+        let ipl = *rom.ipl3.get_ipl();
+        let (crc1, crc2) = CIC::Cic6103.compute_crcs(&ipl, rom.data(), &[]);
+        rom.header.crc1 = crc1;
+        rom.header.crc2 = crc2;
+        assert_eq!(rom.detect_cic_by_crc(), Some(CIC::Cic6103));
+        rom.correct_crc();
+        assert_eq!(rom.detect_cic_by_crc(), Some(CIC::Cic6102));
+    }
+
+    #[test]
+    fn set_ipl3() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        let mut ipl = *rom.ipl3.get_ipl();
+        ipl[0x100] ^= 0xFF;
+        let crcs = rom.set_ipl3(IPL3::from_cic(CIC::Cic6103, ipl));
+        assert_eq!(crcs, CIC::Cic6103.compute_crcs(&ipl, rom.data(), &[]));
+        assert!(rom.check_crc().0);
+        rom.flush().unwrap();
+        assert_eq!(&rom.full()[0x40..HEAD_SIZE], &ipl[..]);
+        assert_eq!(Rom::from_image(rom.full().to_vec()).unwrap().header.crcs(), crcs);
+    }
+
+    #[test]
+    fn copier_header() {
+        let image = test_image();
+        let mut copied = vec![0xAA; COPIER_HEADER_SIZE];
+        copied.extend(&image);
+        let rom = Rom::read(&mut &copied[..]).unwrap();
+        assert!(rom.had_copier_header());
+        assert_eq!(rom.copier_header(), Some(&copied[..COPIER_HEADER_SIZE]));
+        assert_eq!(rom.full(), &image[..]);
+        let rom = Rom::from_image(copied.clone()).unwrap();
+        assert!(rom.had_copier_header());
+        assert_eq!(rom.full(), &image[..]);
+
+        // Head-only reads consume the copier header and head.
+        copied.extend(&[0; 0x100]);
+        let mut reader = CountingReader { inner: &copied[..], count: 0 };
+        let rom = Rom::read_buffered(&mut reader, false).unwrap();
+        assert!(rom.had_copier_header());
+        assert_eq!(reader.count, COPIER_HEADER_SIZE + HEAD_SIZE);
+
+        assert!(!Rom::read(&mut &image[..]).unwrap().had_copier_header());
+        let invalid = vec![0xAA; COPIER_HEADER_SIZE + HEAD_SIZE];
+        let result = Rom::read(&mut &invalid[..]);
+        assert!(matches!(result, Err(Error::HeaderError(crate::header::Error::UnknownByteOrder(0xAAAA_AAAA)))));
+    }
+
+    #[test]
+    fn short_invalid_file() {
+        // Short files report the invalid magic rather than running out of data for a copier header.
+        let invalid = vec![0xAA; COPIER_HEADER_SIZE + Header::SIZE];
+        for len in [Header::SIZE, COPIER_HEADER_SIZE, COPIER_HEADER_SIZE + Header::SIZE - 1] {
+            let result = Rom::read(&mut &invalid[..len]);
+            assert!(matches!(result, Err(Error::HeaderError(crate::header::Error::UnknownByteOrder(0xAAAA_AAAA)))));
+            let result = Rom::read_buffered(&invalid[..len], false);
+            assert!(matches!(result, Err(Error::HeaderError(crate::header::Error::UnknownByteOrder(0xAAAA_AAAA)))));
+        }
+    }
+
+    #[test]
+    fn read_with_limit() {
+        let image = test_image();
+        let rom = Rom::read_with_limit(&mut &image[..], 0x42).unwrap();
+        assert_eq!(rom.len(), HEAD_SIZE + 0x42);
+        let rom = Rom::read_with_limit(&mut &image[..], 0x1000).unwrap();
+        assert_eq!(rom.full(), &image[..]);
+        let rom = Rom::read_with_body(&mut &image[..], false).unwrap();
+        assert_eq!(rom.len(), HEAD_SIZE);
+    }
+
+    #[test]
+    fn finalize() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        rom.data_mut()[0x10] = 0xFF;
+        rom.finalize().unwrap();
+        assert!(rom.check_crc().0);
+        let reread = Rom::from_image(rom.full().to_vec()).unwrap();
+        assert!(reread.check_crc().0);
+        assert_eq!(reread.header.crcs(), rom.header.crcs());
+
+        let mut rom = Rom::from(rom.header, rom.ipl3, vec![0; 0x40], Endianness::Big);
+        assert!(matches!(rom.finalize(), Err(Error::InvalidHeadSize(0x40))));
+    }
+
+    #[test]
+    fn read_counting() {
+        let image = test_image();
+        let (rom, count) = Rom::read_counting(&mut &image[..]).unwrap();
+        assert_eq!(count, image.len());
+        assert_eq!(rom.full(), &image[..]);
+        let (_, count) = Rom::read_counting(&mut &image[..HEAD_SIZE + 0x10]).unwrap();
+        assert_eq!(count, HEAD_SIZE + 0x10);
+
+        let mut copied = vec![0xAA; COPIER_HEADER_SIZE];
+        copied.extend(&image);
+        let (rom, count) = Rom::read_counting(&mut &copied[..]).unwrap();
+        assert_eq!(count, copied.len());
+        assert_eq!(rom.len(), image.len());
+    }
+
+    #[cfg(feature = "hashes")]
+    #[test]
+    fn hashes() {
+        let rom = Rom::from_image(test_image()).unwrap();
+        let hex = |bytes: &[u8]| bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>();
+        let md5 = rom.md5();
+        let sha1 = rom.sha1();
+        assert_eq!(hex(&md5), "66352f20ccfc38aa1038856f5c48d05b");
+        assert_eq!(hex(&sha1), "09ebc998014e8d580e0e974aa2ea25a777619d43");
+        // Hashes are over the full image, so match for a converted copy of the same rom.
+        let mut little = Vec::new();
+        Rom::from_image(test_image()).unwrap().write(&mut little, Some(Endianness::Little)).unwrap();
+        let rom = Rom::read(&mut &little[..]).unwrap();
+        assert_eq!(rom.md5(), md5);
+        assert_eq!(rom.sha1(), sha1);
+    }
+
+    #[test]
+    fn from_parts() {
+        let image = test_image();
+        let (head, body) = image.split_at(HEAD_SIZE);
+        let rom = Rom::from_parts(head, body.to_vec()).unwrap();
+        assert_eq!(rom.full(), &image[..]);
+        assert!(matches!(Rom::from_parts(&head[1..], Vec::new()), Err(Error::InvalidHeadSize(0xFFF))));
+        // Head must be big-endian.
+        let mut little = head.to_vec();
+        little[..4].reverse();
+        assert!(matches!(Rom::from_parts(&little, Vec::new()), Err(Error::UnsupportedEndianness(Endianness::Little))));
+    }
+
+    #[test]
+    fn pad_to_alignment() {
+        let mut image = test_image();
+        image.extend(&[1, 2, 3]);
+        let mut rom = Rom::from_image(image).unwrap();
+        assert_eq!(rom.pad_to_alignment(16, 0xFF), HEAD_SIZE + 0x110);
+        assert_eq!(&rom.full()[HEAD_SIZE + 0x100..HEAD_SIZE + 0x103], &[1, 2, 3]);
+        assert!(rom.full()[HEAD_SIZE + 0x103..].iter().all(|&b| b == 0xFF));
+        // Already aligned data is unchanged.
+        assert_eq!(rom.pad_to_alignment(4, 0), HEAD_SIZE + 0x110);
+    }
+
+    #[test]
+    fn is_bootable() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        rom.correct_crc();
+        // Bootcode is not a known IPL3.
+        assert!(!rom.is_bootable());
+        let ipl = *rom.ipl3.get_ipl();
+        rom.set_ipl3(IPL3::from_cic(CIC::Cic6105, ipl));
+        assert!(rom.is_bootable());
+        rom.header.crc2 ^= 1;
+        assert!(!rom.is_bootable());
+    }
+
+    #[test]
+    fn crc_report() {
+        let mut rom = Rom::from_image(test_image()).unwrap();
+        let report = rom.crc_report();
+        assert!(!report.matches);
+        assert_eq!(report.stored, (0, 0));
+        assert_eq!(report.computed, rom.check_crc().1);
+        assert!(report.to_string().starts_with("Bad CRC values, expected: "));
+        rom.correct_crc();
+        let report = rom.crc_report();
+        assert!(report.matches);
+        assert_eq!(report.stored, report.computed);
+    }
+
+    #[test]
+    fn read_as() {
+        let image = test_image();
+        let rom = Rom::read_as(&mut &image[..], Endianness::Big).unwrap();
+        assert_eq!(rom.full(), &image[..]);
+        let mut little = image.clone();
+        crate::convert::convert(&mut little, Endianness::Big, Endianness::Little).unwrap();
+        assert_eq!(Rom::read_as(&mut &little[..], Endianness::Little).unwrap().order(), Endianness::Little);
+        let mut reader = CountingReader { inner: &little[..], count: 0 };
+        assert!(matches!(Rom::read_as(&mut reader, Endianness::Big), Err(Error::UnsupportedEndianness(Endianness::Little))));
+        assert_eq!(reader.count, Header::SIZE);
+    }
+
+    #[test]
+    fn endianness_from_str() {
+        assert_eq!("big".parse::<Endianness>().unwrap(), Endianness::Big);
+        assert_eq!("little".parse::<Endianness>().unwrap(), Endianness::Little);
+        assert_eq!("mixed".parse::<Endianness>().unwrap(), Endianness::Mixed);
+        assert!(matches!("Big".parse::<Endianness>(), Err(Error::UnknownEndianness(_))));
+        assert_eq!(Endianness::from(FileExt::V64), Endianness::Mixed);
+    }
+
+    /// Reader which counts the bytes read from it.
+    struct CountingReader<R: Read> {
+        inner: R,
+        count: usize,
+    }
+
+    impl<R: Read> Read for CountingReader<R> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.inner.read(buf)?;
+            self.count += read;
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn read_buffered() {
+        let mut image = test_image();
+        image.resize(HEAD_SIZE * 8, 0);
+        let mut reader = CountingReader { inner: &image[..], count: 0 };
+        let rom = Rom::read_buffered(&mut reader, false).unwrap();
+        assert_eq!(rom.len(), HEAD_SIZE);
+        assert_eq!(reader.count, HEAD_SIZE);
+
+        let mut reader = CountingReader { inner: &image[..], count: 0 };
+        let rom = Rom::read_buffered(&mut reader, true).unwrap();
+        assert_eq!(rom.full(), &image[..]);
+        assert_eq!(reader.count, image.len());
+    }
 }
@@ -1,5 +1,6 @@
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use std::fs;
-use std::io;
+use std::io::{self, Read, Write};
 use std::path::Path;
 
 pub const KIBIBYTE: u64 = 1024;
@@ -32,3 +33,38 @@ pub fn update_file_extension(from: impl AsRef<Path>, ext: &str) -> io::Result<bo
     fs::rename(from, to)?;
     Ok(result)
 }
+
+/// Read a 24-bit big-endian integer, such as an IPS record offset.
+pub fn read_u24_be<R: Read>(reader: &mut R) -> io::Result<u32> {
+    reader.read_u24::<BigEndian>()
+}
+
+/// Write a 24-bit big-endian integer, failing if the value does not fit in 24 bits.
+pub fn write_u24_be<W: Write>(writer: &mut W, value: u32) -> io::Result<()> {
+    if value > 0xFF_FFFF {
+        let message = format!("Value does not fit in 24 bits: 0x{:X}", value);
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    }
+    writer.write_u24::<BigEndian>(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn u24_be() {
+        let mut buffer = Vec::new();
+        write_u24_be(&mut buffer, 0x12_3456).unwrap();
+        write_u24_be(&mut buffer, 0xFF_FFFF).unwrap();
+        assert_eq!(buffer, [0x12, 0x34, 0x56, 0xFF, 0xFF, 0xFF]);
+        assert!(write_u24_be(&mut buffer, 0x100_0000).is_err());
+        assert_eq!(buffer.len(), 6);
+
+        let mut reader = Cursor::new(&buffer[..]);
+        assert_eq!(read_u24_be(&mut reader).unwrap(), 0x12_3456);
+        assert_eq!(read_u24_be(&mut reader).unwrap(), 0xFF_FFFF);
+        assert!(read_u24_be(&mut reader).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use zelda64::dma::Table;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(Some(table)) = Table::from_bytes(data) {
+        for entry in &table.entries {
+            let _ = entry.validate();
+            let _ = entry.diff();
+            let _ = entry.to_string();
+        }
+    }
+});
@@ -1,11 +1,13 @@
 use anyhow::Result;
 use clap::{Arg, Command};
-use n64rom::rom::HEAD_SIZE;
-use std::fs::File;
-use std::io::Write;
+use n64rom::rom::{Endianness, HEAD_SIZE};
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::Path;
-use zelda64::decompress;
-use zelda64::rom::Rom;
+use std::process;
+use zelda64::{compress, decompress};
+use zelda64::dma::Table;
+use zelda64::rom::{self, Rom};
 
 fn load_rom(path: &str) -> Result<(Rom, File)> {
     let in_path = Path::new(path);
@@ -14,6 +16,37 @@ fn load_rom(path: &str) -> Result<(Rom, File)> {
     Ok((rom, file))
 }
 
+/// Print table, colored only if output is a terminal.
+#[cfg(feature = "color")]
+fn print_table(table: &Table) {
+    use std::io::IsTerminal;
+    if io::stdout().is_terminal() {
+        println!("{}", table.display_colored());
+    } else {
+        println!("{}", table);
+    }
+}
+
+/// Print table.
+#[cfg(not(feature = "color"))]
+fn print_table(table: &Table) {
+    println!("{}", table);
+}
+
+/// Write `manifest.json` listing table entries and extracted files.
+#[cfg(feature = "serde")]
+fn write_manifest(table: &Table, dir: &Path) -> Result<()> {
+    let manifest = zelda64::manifest::Manifest::from_table(table);
+    fs::write(dir.join("manifest.json"), manifest.to_json()?)?;
+    Ok(())
+}
+
+/// Manifest requires the `serde` feature.
+#[cfg(not(feature = "serde"))]
+fn write_manifest(_table: &Table, _dir: &Path) -> Result<()> {
+    Ok(())
+}
+
 fn main() -> Result<()> {
     let matches = Command::new("zelda64tool")
         .author("saneki <s@neki.me>")
@@ -23,11 +56,29 @@ fn main() -> Result<()> {
             Command::new("decompress")
                 .visible_alias("d")
                 .about("Decompress a Zelda64 rom file")
+                .arg(Arg::new("no-crc")
+                    .long("no-crc")
+                    .takes_value(false)
+                    .help("Do not correct CRC values of the output rom."))
+                .arg(Arg::new("order")
+                    .short('o')
+                    .long("order")
+                    .takes_value(true)
+                    .possible_values(["big", "little", "mixed"])
+                    .help("Byte order of the output rom, instead of the input rom's byte order."))
+                .arg(Arg::new("no-trim")
+                    .long("no-trim")
+                    .takes_value(false)
+                    .help("Do not trim unused space after the last file of the output rom."))
                 .arg(Arg::new("squeeze")
                     .short('s')
                     .long("squeeze")
                     .takes_value(false)
                     .help("Do not match decompressed addresses with virtual addresses."))
+                .arg(Arg::new("skip-errors")
+                    .long("skip-errors")
+                    .takes_value(false)
+                    .help("Zero the output of files which fail to decompress instead of stopping."))
                 .arg(Arg::new("input")
                     .required(true)
                     .help("Input rom file"))
@@ -35,6 +86,43 @@ fn main() -> Result<()> {
                     .required(true)
                     .help("Output rom file"))
         )
+        .subcommand(
+            Command::new("compress")
+                .visible_alias("c")
+                .about("Compress a decompressed Zelda64 rom file")
+                .arg(Arg::new("uncompressed")
+                    .short('u')
+                    .long("uncompressed")
+                    .takes_value(true)
+                    .multiple_occurrences(true)
+                    .help("Table index of a file to keep uncompressed, may be given multiple times."))
+                .arg(Arg::new("input")
+                    .required(true)
+                    .help("Input rom file"))
+                .arg(Arg::new("output")
+                    .required(true)
+                    .help("Output rom file"))
+        )
+        .subcommand(
+            Command::new("entry")
+                .about("Show details about a single table entry of a rom")
+                .arg(Arg::new("rom")
+                    .required(true)
+                    .help("Zelda64 rom file"))
+                .arg(Arg::new("index")
+                    .required(true)
+                    .help("Table index of the entry"))
+        )
+        .subcommand(
+            Command::new("extract")
+                .about("Extract the decompressed files of a rom, named by table index (such as 0002)")
+                .arg(Arg::new("rom")
+                    .required(true)
+                    .help("Zelda64 rom file"))
+                .arg(Arg::new("dir")
+                    .required(true)
+                    .help("Output directory"))
+        )
         .subcommand(
             Command::new("show")
                 .about("Show details about a rom file")
@@ -42,24 +130,132 @@ fn main() -> Result<()> {
                     .required(true)
                     .help("Zelda64 rom file"))
         )
+        .subcommand(
+            Command::new("verify-extract")
+                .about("Verify extracted files against the files of a rom")
+                .arg(Arg::new("rom")
+                    .required(true)
+                    .help("Zelda64 rom file"))
+                .arg(Arg::new("dir")
+                    .required(true)
+                    .help("Directory of extracted files, named by table index (such as 0002)"))
+        )
         .get_matches();
 
     match matches.subcommand() {
         Some(("decompress", matches)) => {
             let in_path = matches.value_of("input").unwrap();
-            let (rom, _) = load_rom(&in_path)?;
+            let (rom, _) = load_rom(in_path)?;
             let squeeze = matches.is_present("squeeze");
-            let mut dec_rom = decompress::decompress(&rom, !squeeze)?;
+            let correct_crc = !matches.is_present("no-crc");
+            let options = decompress::Options {
+                keep_crc: !correct_crc,
+                skip_errors: matches.is_present("skip-errors"),
+                ..Default::default()
+            };
+            let mut dec_rom = match decompress::decompress_with_failures(&rom, !squeeze, &options) {
+                Ok((dec_rom, failures)) => {
+                    for index in failures {
+                        println!("[{}] Failed to decompress, output zeroed", index);
+                    }
+                    dec_rom
+                }
+                Err(decompress::Error::NoTable) => {
+                    println!("No table?");
+                    process::exit(1);
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            if !matches.is_present("no-trim") {
+                dec_rom.trim();
+            }
+
+            let out_path = matches.value_of("output").unwrap();
+            let mut out_file = File::create(out_path)?;
+            let order = matches.value_of("order").map(str::parse::<Endianness>).transpose()?;
+            dec_rom.update_with_crc(correct_crc)?;
+            let (written, order) = dec_rom.rom.write_ordered(&mut out_file, order)?;
+            out_file.flush()?;
+            println!("Wrote {:08X} bytes in {} byte order!", written, order);
+        }
+        Some(("compress", matches)) => {
+            let in_path = matches.value_of("input").unwrap();
+            let (rom, _) = load_rom(in_path)?;
+            let uncompressed = matches.values_of("uncompressed")
+                .into_iter()
+                .flatten()
+                .map(str::parse)
+                .collect::<Result<_, _>>()?;
+            let options = compress::Options { uncompressed, ..Default::default() };
+            let mut com_rom = match compress::compress_with_options(&rom, &options) {
+                Ok(com_rom) => com_rom,
+                Err(compress::Error::NoTable) => {
+                    println!("No table?");
+                    process::exit(1);
+                }
+                Err(err) => return Err(err.into()),
+            };
 
             let out_path = matches.value_of("output").unwrap();
             let mut out_file = File::create(out_path)?;
-            let written = dec_rom.write_with_update(&mut out_file)?;
+            let written = com_rom.write(&mut out_file)?;
             out_file.flush()?;
-            println!("Wrote {:08X} bytes!", written);
+            let saved = rom.rom.len().saturating_sub(written);
+            println!("Wrote {:08X} bytes, {:08X} bytes smaller than the input rom!", written, saved);
+        }
+        Some(("entry", matches)) => {
+            let path = matches.value_of("rom").unwrap();
+            let (rom, _) = load_rom(path)?;
+            let index: usize = matches.value_of("index").unwrap().parse()?;
+            let entry = match rom.entry(index) {
+                Ok(entry) => entry,
+                Err(rom::Error::NoTable) => {
+                    println!("No table?");
+                    process::exit(1);
+                }
+                Err(rom::Error::IndexError(index)) => {
+                    let count = rom.table.as_ref().map_or(0, |table| table.entries.len());
+                    println!("Entry index out-of-range: {} (table has {} entries)", index, count);
+                    process::exit(1);
+                }
+                Err(err) => return Err(err.into()),
+            };
+
+            println!("Entry {}: {}", index, entry);
+            match entry.range().0 {
+                Some(range) => println!("  Physical Range: 0x{:08X}-0x{:08X}", range.start, range.end),
+                None => println!("  Physical Range: None"),
+            }
+            println!("  Decompressed Size: 0x{:X}", entry.decompressed_len());
+            let yaz0 = entry.is_compressed() && rom.is_yaz0(entry);
+            println!("  Yaz0: {}", if yaz0 { "Yes" } else { "No" });
+        }
+        Some(("extract", matches)) => {
+            let path = matches.value_of("rom").unwrap();
+            let (rom, _) = load_rom(path)?;
+            let dir = Path::new(matches.value_of("dir").unwrap());
+            let table = match &rom.table {
+                Some(table) => table,
+                None => {
+                    println!("No table?");
+                    process::exit(1);
+                }
+            };
+
+            fs::create_dir_all(dir)?;
+            let mut count = 0;
+            for file in rom.files() {
+                let file = file?;
+                fs::write(dir.join(rom::file_name(file.index)), file.data)?;
+                count += 1;
+            }
+            write_manifest(table, dir)?;
+            println!("Extracted {} files!", count);
         }
         Some(("show", matches)) => {
             let path = matches.value_of("file").unwrap();
-            let (rom, _) = load_rom(&path)?;
+            let (rom, _) = load_rom(path)?;
 
             match &rom.table {
                 Some(table) => {
@@ -67,11 +263,65 @@ fn main() -> Result<()> {
                     let offset = (table.address as usize) + HEAD_SIZE;
 
                     println!("Table: 0x{:08X}", offset);
-                    println!("{}", table);
+                    if let Err(err) = table.verify() {
+                        println!("Warning: {}", err);
+                    }
+                    print_table(table);
+                    println!("{}", table.report());
+                    if let Some(expected) = rom.expected_size() {
+                        // Retail roms are padded to a power of two size after the last file.
+                        let len = rom.rom.len();
+                        if len < expected {
+                            println!("Warning: Rom is smaller than its data (underdump of {} bytes)", expected - len);
+                        } else if len > expected.next_power_of_two() {
+                            let size = expected.next_power_of_two();
+                            println!("Warning: Rom is larger than its data (overdump of {} bytes)", len - size);
+                        }
+                    }
                 },
                 None => println!("No table?")
             }
         }
+        Some(("verify-extract", matches)) => {
+            let path = matches.value_of("rom").unwrap();
+            let (rom, _) = load_rom(path)?;
+            let dir = Path::new(matches.value_of("dir").unwrap());
+            let count = match &rom.table {
+                Some(table) => table.entries.len(),
+                None => {
+                    println!("No table?");
+                    process::exit(1);
+                }
+            };
+
+            let mut failures = 0;
+            for index in 0..count {
+                // Entries without file data are not extracted.
+                let expected = match rom.extract(index)? {
+                    Some(expected) => expected,
+                    None => continue,
+                };
+                let file_path = dir.join(rom::file_name(index));
+                match fs::read(&file_path) {
+                    Ok(actual) if actual == expected => {}
+                    Ok(_) => {
+                        println!("[{}] Mismatch: {}", index, file_path.display());
+                        failures += 1;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                        println!("[{}] Missing: {}", index, file_path.display());
+                        failures += 1;
+                    }
+                    Err(err) => return Err(err.into()),
+                }
+            }
+
+            if failures != 0 {
+                println!("{} file(s) failed verification", failures);
+                process::exit(1);
+            }
+            println!("All files match!");
+        }
         None => {
             println!("No subcommand was used");
         }
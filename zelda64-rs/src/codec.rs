@@ -0,0 +1,252 @@
+use byteorder::{BigEndian, ByteOrder};
+use std::fmt;
+use std::io::Cursor;
+use thiserror::Error;
+use yaz0::inflate::Yaz0Archive;
+use yaz0::{CompressionLevel, Yaz0Writer};
+
+use crate::dma::EntryType;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Compressed file data has unknown magic: 0x{0:08X}")]
+    UnknownMagic(u32),
+    #[error("Output buffer too small for decompressed data: expected 0x{0:X} bytes, found 0x{1:X}")]
+    OutputTooSmall(usize, usize),
+    #[error("Compressed data is truncated")]
+    Truncated,
+    #[error("Compressed data refers back 0x{0:X} bytes at output offset 0x{1:X}")]
+    InvalidBackReference(usize, usize),
+    #[error("Decompressed size 0x{0:X} exceeds limit of 0x{1:X} bytes")]
+    OutputLimitExceeded(usize, usize),
+    #[error("Compression is not supported for codec: {0}")]
+    UnsupportedCompression(Codec),
+    #[error("Yaz0 decompression error: {0}")]
+    Yaz0Error(#[from] ::yaz0::Error),
+}
+
+/// Compression codec of file data, detected from its magic.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Codec {
+    /// Yaz0 compressed, magic `Yaz0`.
+    Yaz0,
+    /// Yay0 compressed, magic `Yay0`.
+    Yay0,
+    /// No known magic, data is assumed to be stored raw.
+    Raw,
+}
+
+impl Codec {
+    /// Detect codec from the first 4 bytes of data.
+    pub fn detect(data: &[u8]) -> Self {
+        match data.get(..4) {
+            Some(b"Yaz0") => Self::Yaz0,
+            Some(b"Yay0") => Self::Yay0,
+            _ => Self::Raw,
+        }
+    }
+
+    /// Get codec for file data of an entry with the given `EntryType`.
+    ///
+    /// Data of compressed entries must have a known magic, while all other data is raw.
+    pub fn from_kind(kind: EntryType, data: &[u8]) -> Result<Self, Error> {
+        match kind {
+            EntryType::Compressed => match Self::detect(data) {
+                Self::Raw => {
+                    let magic = data.get(..4).map(BigEndian::read_u32).unwrap_or(0);
+                    Err(Error::UnknownMagic(magic))
+                }
+                codec => Ok(codec),
+            },
+            _ => Ok(Self::Raw),
+        }
+    }
+
+    /// Get the decompressed size of data, from the header if compressed.
+    pub fn decompressed_size(self, data: &[u8]) -> Result<usize, Error> {
+        match self {
+            Self::Raw => Ok(data.len()),
+            _ => Ok(read_u32(data, 4)? as usize),
+        }
+    }
+
+    /// Decompress data into the beginning of the output buffer, returning the decompressed size.
+    pub fn decompress_into(self, data: &[u8], output: &mut [u8]) -> Result<usize, Error> {
+        let size = self.decompressed_size(data)?;
+        let available = output.len();
+        let output = output.get_mut(..size).ok_or(Error::OutputTooSmall(size, available))?;
+        match self {
+            Self::Yaz0 => {
+                let mut archive = Yaz0Archive::new(Cursor::new(data))?;
+                archive.decompress_into(output)?;
+            }
+            Self::Yay0 => yay0_decompress_into(data, output)?,
+            Self::Raw => output.copy_from_slice(data),
+        }
+        Ok(size)
+    }
+
+    /// Decompress data into a new buffer.
+    pub fn decompress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        self.decompress_with_limit(data, usize::MAX)
+    }
+
+    /// Decompress data into a new buffer, failing before allocating if the size from the header exceeds `max_output`.
+    ///
+    /// Compressed headers may claim any size up to 4 GiB, so this guards against untrusted data. Raw data is not
+    /// limited, as it is already in memory.
+    pub fn decompress_with_limit(self, data: &[u8], max_output: usize) -> Result<Vec<u8>, Error> {
+        let size = self.decompressed_size(data)?;
+        if size > max_output && self != Self::Raw {
+            return Err(Error::OutputLimitExceeded(size, max_output));
+        }
+        let mut output = vec![0; size];
+        self.decompress_into(data, &mut output)?;
+        Ok(output)
+    }
+
+    /// Compress data into a new buffer. Raw data is copied as-is.
+    ///
+    /// Only Yaz0 compression is currently supported.
+    pub fn compress(self, data: &[u8]) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Yaz0 => {
+                let mut output = Vec::new();
+                let writer = Yaz0Writer::new(&mut output);
+                writer.compress_and_write(data, CompressionLevel::Lookahead { quality: 10 })?;
+                Ok(output)
+            }
+            Self::Yay0 => Err(Error::UnsupportedCompression(self)),
+            Self::Raw => Ok(data.to_vec()),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Yaz0 => write!(f, "Yaz0"),
+            Self::Yay0 => write!(f, "Yay0"),
+            Self::Raw => write!(f, "Raw"),
+        }
+    }
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16, Error> {
+    data.get(offset..offset + 2).map(BigEndian::read_u16).ok_or(Error::Truncated)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32, Error> {
+    data.get(offset..offset + 4).map(BigEndian::read_u32).ok_or(Error::Truncated)
+}
+
+fn read_u8(data: &[u8], offset: usize) -> Result<u8, Error> {
+    data.get(offset).cloned().ok_or(Error::Truncated)
+}
+
+/// Decompress Yay0 data to fill the output buffer.
+///
+/// Unlike Yaz0, Yay0 stores three separate streams: 32-bit mask words following the header, 16-bit back-references
+/// at the link table offset, and literal bytes (plus extended run lengths) at the chunk offset.
+fn yay0_decompress_into(data: &[u8], output: &mut [u8]) -> Result<(), Error> {
+    let mut mask_offset = 0x10;
+    let mut link_offset = read_u32(data, 0x8)? as usize;
+    let mut chunk_offset = read_u32(data, 0xC)? as usize;
+    let mut mask = 0;
+    let mut bits = 0;
+    let mut position = 0;
+
+    while position < output.len() {
+        if bits == 0 {
+            mask = read_u32(data, mask_offset)?;
+            mask_offset += 4;
+            bits = 32;
+        }
+
+        if mask & 0x8000_0000 != 0 {
+            // Copy literal byte from chunk stream.
+            output[position] = read_u8(data, chunk_offset)?;
+            chunk_offset += 1;
+            position += 1;
+        } else {
+            // Copy run from previous output, extended lengths are stored in the chunk stream.
+            let link = read_u16(data, link_offset)?;
+            link_offset += 2;
+            let distance = (link & 0xFFF) as usize + 1;
+            let count = match link >> 12 {
+                0 => {
+                    let length = read_u8(data, chunk_offset)? as usize + 0x12;
+                    chunk_offset += 1;
+                    length
+                }
+                n => n as usize + 2,
+            };
+            if distance > position {
+                return Err(Error::InvalidBackReference(distance, position));
+            }
+            let end = (position + count).min(output.len());
+            for idx in position..end {
+                output[idx] = output[idx - distance];
+            }
+            position = end;
+        }
+
+        mask <<= 1;
+        bits -= 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build Yay0 data from its streams.
+    fn yay0(size: u32, masks: &[u32], links: &[u16], chunks: &[u8]) -> Vec<u8> {
+        let link_offset = 0x10 + (masks.len() * 4);
+        let chunk_offset = link_offset + (links.len() * 2);
+        let mut data = b"Yay0".to_vec();
+        for &value in &[size, link_offset as u32, chunk_offset as u32] {
+            data.extend(&value.to_be_bytes());
+        }
+        for mask in masks {
+            data.extend(&mask.to_be_bytes());
+        }
+        for link in links {
+            data.extend(&link.to_be_bytes());
+        }
+        data.extend(chunks);
+        data
+    }
+
+    #[test]
+    fn yay0_short_run() {
+        // Literals "abc", then run of 6 bytes from 3 bytes back.
+        let data = yay0(9, &[0xE000_0000], &[0x4002], b"abc");
+        assert_eq!(Codec::detect(&data), Codec::Yay0);
+        assert_eq!(Codec::Yay0.decompress(&data).unwrap(), b"abcabcabc");
+    }
+
+    #[test]
+    fn yay0_long_run() {
+        // Literal "z", then run of 0x12 + 0x10 bytes from 1 byte back.
+        let data = yay0(0x23, &[0x8000_0000], &[0x0000], &[b'z', 0x10]);
+        assert_eq!(Codec::Yay0.decompress(&data).unwrap(), vec![b'z'; 0x23]);
+    }
+
+    #[test]
+    fn yay0_invalid() {
+        let data = yay0(4, &[0x0000_0000], &[0x1000], &[]);
+        assert!(matches!(Codec::Yay0.decompress(&data), Err(Error::InvalidBackReference(1, 0))));
+        let data = yay0(4, &[0x8000_0000], &[], &[]);
+        assert!(matches!(Codec::Yay0.decompress(&data), Err(Error::Truncated)));
+    }
+
+    #[test]
+    fn output_too_small() {
+        let data = yay0(9, &[0xE000_0000], &[0x4002], b"abc");
+        let mut output = [0; 8];
+        assert!(matches!(Codec::Yay0.decompress_into(&data, &mut output), Err(Error::OutputTooSmall(9, 8))));
+    }
+}
@@ -0,0 +1,139 @@
+use n64rom::rom::Rom as N64Rom;
+use std::collections::HashSet;
+use thiserror::Error;
+
+use crate::codec::{self, Codec};
+use crate::dma::{self, Entry, EntryType, Table};
+use crate::rom::{self, Rom};
+use crate::util;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("{0}")]
+    CodecError(#[from] codec::Error),
+    #[error("{0}")]
+    DmaError(#[from] dma::Error),
+    #[error("{0}")]
+    RomError(#[from] rom::Error),
+    #[error("Rom does not contain a dmadata table")]
+    NoTable,
+    #[error("Table does not contain an entry for itself")]
+    NoTableEntry,
+}
+
+/// Options used when compressing.
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    /// Table indexes of additional files to keep uncompressed, such as files which are read partially.
+    pub uncompressed: HashSet<usize>,
+    /// Byte used to fill alignment padding between files.
+    pub fill_byte: u8,
+}
+
+/// Compress `dmadata` filesystem in ROM with default `Options`.
+pub fn compress(rom: &Rom) -> Result<Rom, Error> {
+    compress_with_options(rom, &Options::default())
+}
+
+/// Compress `dmadata` filesystem in ROM with given `Options`, packing files in table order with 16-byte alignment.
+///
+/// Files up to and including the table itself are kept uncompressed, as they are loaded before the table is
+/// available. Files which are already compressed are copied as-is, and files which would not shrink are kept
+/// uncompressed. Table data is updated and CRC values are corrected.
+pub fn compress_with_options(rom: &Rom, options: &Options) -> Result<Rom, Error> {
+    let table = rom.table.as_ref().ok_or(Error::NoTable)?;
+    let table_index = table.entries.iter().position(|entry| entry.virt_start() == table.address)
+        .ok_or(Error::NoTableEntry)?;
+    let mut image = Vec::with_capacity(rom.rom.len());
+    let mut entries = Vec::with_capacity(table.entries.len());
+
+    for (index, entry) in table.entries.iter().enumerate() {
+        let (virt, range, kind) = entry.validate()?;
+        if range.is_none() {
+            entries.push(entry.clone());
+            continue;
+        }
+        let input = rom.slice(entry)?;
+        let phys = image.len() as u32;
+        let keep = index <= table_index || options.uncompressed.contains(&index);
+        let new_entry = match kind {
+            EntryType::Compressed => {
+                image.extend_from_slice(input);
+                Entry::from(virt.start, virt.end, phys, image.len() as u32)
+            }
+            _ if index == table_index => {
+                // Table data is written once all entries are known.
+                image.resize(image.len() + input.len().max(table.size()), 0);
+                Entry::from_uncompressed(virt.start, virt.end, phys)
+            }
+            _ => {
+                let compressed = if keep { None } else { Some(Codec::Yaz0.compress(input)?) };
+                match compressed {
+                    Some(compressed) if compressed.len() < input.len() => {
+                        image.extend(compressed);
+                        Entry::from(virt.start, virt.end, phys, image.len() as u32)
+                    }
+                    _ => {
+                        image.extend_from_slice(input);
+                        Entry::from_uncompressed(virt.start, virt.end, phys)
+                    }
+                }
+            }
+        };
+        image.resize(util::align16(image.len() as u32) as usize, options.fill_byte);
+        entries.push(new_entry);
+    }
+
+    let n64rom = N64Rom::from(rom.rom.header, rom.rom.ipl3, image, rom.rom.order());
+    let mut new_rom = Rom::from(n64rom, Some(Table::from(table.address, entries)));
+    new_rom.update()?;
+    Ok(new_rom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    use crate::decompress;
+    use crate::testing;
+
+    #[test]
+    fn compress_round_trip() {
+        let data: Vec<u8> = (0..0x200).map(|i| (i / 0x20) as u8).collect();
+        let other: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false), (&other, false)]);
+        let dec = decompress::decompress(&rom, true).unwrap();
+
+        let options = Options { uncompressed: std::iter::once(4).collect(), ..Options::default() };
+        let com = compress_with_options(&dec, &options).unwrap();
+        assert!(com.rom.len() < dec.rom.len());
+        assert!(com.rom.check_crc().0);
+        let entries = &com.table.as_ref().unwrap().entries;
+        // Head and table are kept uncompressed, as are files which do not shrink.
+        assert!(entries[0].is_decompressed());
+        assert!(entries[1].is_decompressed());
+        assert!(entries[2].is_compressed());
+        assert!(entries[3].is_decompressed());
+        assert!(entries[4].is_decompressed());
+        assert_eq!(entries[1].phys_start(), testing::TABLE_ADDRESS);
+
+        let reread = Rom::read(&mut Cursor::new(com.rom.full())).unwrap();
+        assert_eq!(&reread.table.as_ref().unwrap().entries, entries);
+        assert_eq!(reread.extract(2).unwrap().unwrap(), data);
+        assert_eq!(reread.extract(3).unwrap().unwrap(), vec![1, 2, 3]);
+        assert_eq!(reread.extract(4).unwrap().unwrap(), other);
+    }
+
+    #[test]
+    fn compress_fill_byte() {
+        let data: Vec<u8> = (0..0x200).map(|i| (i / 0x20) as u8).collect();
+        let dec = decompress::decompress(&testing::build_rom(&[(&data, true), (&[1, 2, 3], false)]), true).unwrap();
+        let options = Options { fill_byte: 0xFF, ..Options::default() };
+        let com = compress_with_options(&dec, &options).unwrap();
+        let gaps = testing::uncovered_bytes(&com);
+        assert!(!gaps.is_empty());
+        assert!(gaps.iter().all(|&b| b == 0xFF));
+        assert!(testing::uncovered_bytes(&compress(&dec).unwrap()).iter().all(|&b| b == 0));
+    }
+}
@@ -1,10 +1,9 @@
 use n64rom::rom::Rom as N64Rom;
-use std::io::Cursor;
-use std::ops::Range;
+use std::ops::{Bound, Range, RangeBounds};
 use thiserror::Error;
-use yaz0::inflate::Yaz0Archive;
 
-use crate::dma::{self, Entry, EntryType, Table};
+use crate::codec::{self, Codec};
+use crate::dma::{self, Entry, Table};
 use crate::rom::{self, Rom};
 use crate::util::{self, ConvertRangeExt};
 
@@ -13,38 +12,154 @@ const ROM_CAPACITY: usize = 1024 * 1024 * 64;
 
 #[derive(Debug, Error)]
 pub enum Error {
+    #[error("{0}")]
+    CodecError(#[from] codec::Error),
     #[error("{0}")]
     DmaError(#[from] dma::Error),
     #[error("{0}")]
     RomError(#[from] rom::Error),
+    #[error("Failed to decompress entry {index} with virtual range (0x{:08X}, 0x{:08X}): {source}", .range.start, .range.end)]
+    EntryDecompress {
+        index: usize,
+        range: Range<u32>,
+        source: codec::Error,
+    },
+    #[error("Rom does not contain a dmadata table")]
+    NoTable,
+    #[error("Decompressed size of entry {index} is 0x{actual:X}, expected 0x{expected:X}")]
+    SizeMismatch {
+        index: usize,
+        expected: usize,
+        actual: usize,
+    },
     #[error("Address out-of-range for output slice: (0x{:8X}, 0x{:8X})", .0.start, .0.end)]
     OutOfRangeError(Range<u32>),
     #[error("Yaz0 decompression error: {0}")]
     Yaz0Error(#[from] ::yaz0::Error),
 }
 
+/// Options used when decompressing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Options {
+    /// Byte used to fill regions of the output rom which are not covered by any file.
+    pub fill_byte: u8,
+    /// Whether or not to keep the original header CRC values, instead of correcting them for the decompressed data.
+    pub keep_crc: bool,
+    /// Maximum decompressed size of any single compressed file, if limited.
+    pub max_output: Option<usize>,
+    /// Whether or not to continue past files which fail to decompress, zeroing their output instead.
+    ///
+    /// Failed table indexes are returned by `decompress_with_failures`.
+    pub skip_errors: bool,
+}
+
 /// Decompress `dmadata` filesystem in ROM with default `Options`.
 pub fn decompress(rom: &Rom, matching: bool) -> Result<Rom, Error> {
+    decompress_with_options(rom, matching, &Options::default())
+}
+
+/// Decompress `dmadata` filesystem in ROM with given `Options`.
+///
+/// Table data of the output rom is updated, and CRC values are corrected unless `Options::keep_crc` is set.
+pub fn decompress_with_options(rom: &Rom, matching: bool, options: &Options) -> Result<Rom, Error> {
+    decompress_with_failures(rom, matching, options).map(|(rom, _)| rom)
+}
+
+/// Decompress `dmadata` filesystem in ROM with given `Options`, along with the table indexes of files which failed to
+/// decompress if `Options::skip_errors` is set.
+pub fn decompress_with_failures(rom: &Rom, matching: bool, options: &Options) -> Result<(Rom, Vec<usize>), Error> {
     if matching {
-        decompress_with_matching::<true>(rom)
+        decompress_with_failures_matching::<true>(rom, options)
     } else {
-        decompress_with_matching::<false>(rom)
+        decompress_with_failures_matching::<false>(rom, options)
     }
 }
 
+/// Decompress only the files of the table entries in the given index range, without building an output ROM.
+///
+/// Entries without file data produce an empty `Vec`. Fails with `rom::Error::IndexError` if the range extends past
+/// the end of the table, before decompressing any file.
+pub fn decompress_range(rom: &Rom, indexes: impl RangeBounds<usize>) -> Result<Vec<Vec<u8>>, Error> {
+    let table = rom.table.as_ref().ok_or(Error::NoTable)?;
+    let count = table.entries.len();
+    let start = match indexes.start_bound() {
+        Bound::Included(&index) => index,
+        Bound::Excluded(&index) => index.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match indexes.end_bound() {
+        Bound::Included(&index) => index.checked_add(1),
+        Bound::Excluded(&index) => Some(index),
+        Bound::Unbounded => Some(count),
+    };
+    // The first index past the end of the table is reported, as `Rom::extract` would.
+    let end = end.filter(|&end| end <= count).ok_or(rom::Error::IndexError(count))?;
+    let mut files = Vec::with_capacity(end.saturating_sub(start));
+    for index in start..end {
+        let file = rom.extract(index)?.unwrap_or_default();
+        files.push(file);
+    }
+    Ok(files)
+}
+
+/// Decompress `dmadata` filesystem in ROM into a caller-provided buffer, returning the rebuilt `Table`.
+///
+/// Bytes of `out` which are not covered by any file are left unchanged. Fails with `Error::OutOfRangeError` if a
+/// file does not fit in `out`.
+pub fn decompress_into_slice(rom: &Rom, out: &mut [u8], matching: bool) -> Result<Table, Error> {
+    let (table, _) = if matching {
+        decompress_into_slice_with_matching::<true>(rom, out, &Options::default())?
+    } else {
+        decompress_into_slice_with_matching::<false>(rom, out, &Options::default())?
+    };
+    Ok(table)
+}
+
 /// Decompress `dmadata` filesystem in ROM with given `Options`.
-pub fn decompress_with_matching<const MATCHING: bool>(rom: &Rom) -> Result<Rom, Error> {
+pub fn decompress_with_matching<const MATCHING: bool>(rom: &Rom, options: &Options) -> Result<Rom, Error> {
+    decompress_with_failures_matching::<MATCHING>(rom, options).map(|(rom, _)| rom)
+}
+
+/// Decompress `dmadata` filesystem in ROM with given `Options`, along with the table indexes of failed files.
+fn decompress_with_failures_matching<const MATCHING: bool>(
+    rom: &Rom,
+    options: &Options,
+) -> Result<(Rom, Vec<usize>), Error> {
     let n64rom = &rom.rom;
-    let mut data = vec![0; ROM_CAPACITY];
-    let table = rom.table.as_ref().unwrap();
+    let mut data = vec![options.fill_byte; ROM_CAPACITY];
+    let (new_table, failures) = decompress_into_slice_with_matching::<MATCHING>(rom, &mut data, options)?;
+    let new_n64rom = N64Rom::from(n64rom.header, n64rom.ipl3, data, n64rom.order());
+    let mut new_rom = Rom::from(new_n64rom, Some(new_table));
+    new_rom.update_with_crc(!options.keep_crc)?;
+
+    Ok((new_rom, failures))
+}
+
+/// Check the decompressed size of compressed data against an optional limit. Raw data cannot expand, so is not checked.
+fn check_limit(codec: Codec, data: &[u8], max_output: Option<usize>) -> Result<Codec, codec::Error> {
+    match max_output {
+        Some(max) if codec != Codec::Raw => {
+            let size = codec.decompressed_size(data)?;
+            if size > max { Err(codec::Error::OutputLimitExceeded(size, max)) } else { Ok(codec) }
+        }
+        _ => Ok(codec),
+    }
+}
+
+fn decompress_into_slice_with_matching<const MATCHING: bool>(
+    rom: &Rom,
+    data: &mut [u8],
+    options: &Options,
+) -> Result<(Table, Vec<usize>), Error> {
+    let table = rom.table.as_ref().ok_or(Error::NoTable)?;
     let mut entries = Vec::with_capacity(table.entries.len());
+    let mut failures = Vec::new();
     let mut offset = 0;
 
-    for entry in &table.entries {
+    for (index, entry) in table.entries.iter().enumerate() {
         let (virt, range, kind) = entry.validate()?;
         match range {
             Some(_) => {
-                let input = rom.slice(&entry);
                 // Either use virtual addresses for output slice, or begin where last slice ended.
                 let outrange = if MATCHING {
                     virt.clone()
@@ -56,28 +171,226 @@ pub fn decompress_with_matching<const MATCHING: bool>(rom: &Rom) -> Result<Rom,
                 };
                 // Append new Entry and get mutable slice for output.
                 entries.push(Entry::from_uncompressed(virt.start, virt.end, outrange.start));
-                let mut output = data.get_mut(outrange.to_usize()).ok_or(Error::OutOfRangeError(outrange))?;
-                match kind {
-                    EntryType::Compressed => {
-                        // Decompress Yaz0-compressed file data.
-                        let mut cursor = Cursor::new(input);
-                        let mut archive = Yaz0Archive::new(&mut cursor)?;
-                        archive.decompress_into(&mut output)?;
-                    }
-                    EntryType::Decompressed => {
-                        // Direct copy as file data is not compressed.
-                        output.copy_from_slice(input);
+                // Zero-length files have nothing to decompress, and their stored data may be empty or past the end.
+                if virt.is_empty() {
+                    continue;
+                }
+                let output = data.get_mut(outrange.to_usize()).ok_or(Error::OutOfRangeError(outrange))?;
+                let result = rom.slice(entry).map_err(Error::from).and_then(|input| {
+                    Codec::from_kind(kind, input)
+                        .and_then(|codec| check_limit(codec, input, options.max_output))
+                        .and_then(|codec| codec.decompress_into(input, output))
+                        .map_err(|source| Error::EntryDecompress { index, range: virt.clone(), source })
+                });
+                // Decompressed data shorter than the virtual range would leave the tail of the file unwritten, holding
+                // the fill byte rather than file data.
+                let expected = virt.len();
+                let result = result.and_then(|actual| match actual == expected {
+                    true => Ok(actual),
+                    false => Err(Error::SizeMismatch { index, expected, actual }),
+                });
+                match result {
+                    Ok(_) => {}
+                    Err(_) if options.skip_errors => {
+                        // Output may be partially written before failing.
+                        output.fill(0);
+                        failures.push(index);
                     }
-                    _ => unreachable!()
+                    Err(err) => return Err(err),
                 }
             }
             _ => entries.push(entry.clone())
         }
     }
 
-    let new_table = Table::from(table.address, entries);
-    let new_n64rom = N64Rom::from(n64rom.header, n64rom.ipl3, data, n64rom.order());
-    let new_rom = Rom::from(new_n64rom, Some(new_table));
+    Ok((Table::from(table.address, entries), failures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use n64rom::header::Magic;
+    use n64rom::rom::HEAD_SIZE;
+
+    use crate::testing;
+
+    #[test]
+    fn decompress_fill_byte() {
+        // File is placed at 0x1090, after the 3-entry table.
+        let rom = testing::build_rom(&[(&[1, 2, 3, 4, 5], false)]);
+        let options = Options { fill_byte: 0xFF, keep_crc: true, ..Options::default() };
+        let dec = decompress_with_options(&rom, false, &options).unwrap();
+        let data = dec.rom.full();
+        assert_eq!(&data[0x1090..0x1095], &[1, 2, 3, 4, 5]);
+        assert!(data[0x1095..0x10A0].iter().all(|&b| b == 0xFF));
+        assert!(data[0x10A0..].iter().all(|&b| b == 0xFF));
+        // Header is copied from the first file.
+        assert_eq!(&data[..4], Magic::new().as_ref());
+        assert!(data[4..HEAD_SIZE].iter().all(|&b| b == 0));
+    }
 
-    Ok(new_rom)
+    #[test]
+    fn decompress_fill_byte_matching() {
+        // Files are placed at their virtual addresses: 0x10B0 (compressed), 0x10F0 and 0x1100.
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false), (&[4], false)]);
+        let options = Options { fill_byte: 0xFF, ..Options::default() };
+        let dec = decompress_with_options(&rom, true, &options).unwrap();
+        let data = dec.rom.full();
+        assert_eq!(&data[0x10B0..0x10F0], &(0..0x40).collect::<Vec<u8>>()[..]);
+        assert_eq!(&data[0x10F0..0x10F3], &[1, 2, 3]);
+        assert!(data[0x10F3..0x1100].iter().all(|&b| b == 0xFF));
+        assert_eq!(data[0x1100], 4);
+        assert!(data[0x1101..].iter().all(|&b| b == 0xFF));
+    }
+
+    #[test]
+    fn decompress_keep_crc() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false)]);
+        let dec = decompress(&rom, true).unwrap();
+        assert!(dec.rom.check_crc().0);
+        // Table data of the output is updated either way.
+        let reread = Rom::read(&mut std::io::Cursor::new(dec.rom.full())).unwrap();
+        assert_eq!(reread.table.unwrap().entries, dec.table.as_ref().unwrap().entries);
+
+        let options = Options { keep_crc: true, ..Options::default() };
+        let dec = decompress_with_options(&rom, true, &options).unwrap();
+        assert_eq!(dec.rom.header.crcs(), rom.rom.header.crcs());
+        assert_eq!(dec.rom.full()[0x10..0x18], rom.rom.full()[0x10..0x18]);
+        assert!(!dec.rom.check_crc().0);
+    }
+
+    #[test]
+    fn decompress_range_files() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false), (&[4], false)]);
+        let files = decompress_range(&rom, 2..4).unwrap();
+        assert_eq!(files, vec![data.clone(), vec![1, 2, 3]]);
+        let files = decompress_range(&rom, 3..).unwrap();
+        assert_eq!(files, vec![vec![1, 2, 3], vec![4]]);
+        assert!(decompress_range(&rom, 4..=5).is_err());
+        for result in [decompress_range(&rom, 0..usize::MAX), decompress_range(&rom, ..=usize::MAX)] {
+            assert!(matches!(result, Err(Error::RomError(rom::Error::IndexError(5)))));
+        }
+        assert!(decompress_range(&rom, (Bound::Excluded(usize::MAX), Bound::Unbounded)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn decompress_fill_byte_default() {
+        let rom = testing::build_rom(&[(&[1, 2, 3, 4, 5], false)]);
+        let dec = decompress(&rom, false).unwrap();
+        assert!(dec.rom.full()[0x1095..].iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn decompress_into_slice_buffer() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&data, true)]);
+        let mut out = vec![0xAA; 0x10D0];
+        let table = decompress_into_slice(&rom, &mut out, true).unwrap();
+        assert_eq!(&out[0x1090..0x10D0], &data[..]);
+        assert_eq!(table.entries[2], Entry::from_uncompressed(0x1090, 0x10D0, 0x1090));
+        // Too small for the last file.
+        let mut out = vec![0; 0x10C0];
+        assert!(matches!(decompress_into_slice(&rom, &mut out, true), Err(Error::OutOfRangeError(_))));
+    }
+
+    #[test]
+    fn decompress_entry_error() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let mut rom = testing::build_rom(&[(&[1, 2, 3], false), (&data, true)]);
+        let offset = rom.entry(3).unwrap().phys_start() as usize;
+        rom.rom.full_mut()[offset..offset + 4].copy_from_slice(b"Bad0");
+        match decompress(&rom, false) {
+            Err(Error::EntryDecompress { index, range, source }) => {
+                assert_eq!(index, 3);
+                assert_eq!(range, rom.entry(3).unwrap().virt());
+                assert!(matches!(source, codec::Error::UnknownMagic(_)));
+            }
+            _ => panic!("expected EntryDecompress error"),
+        }
+    }
+
+    #[test]
+    fn decompress_size_mismatch() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let (image, mut entries) = testing::build_image(&[(&data, true)]);
+        let mut rom = Rom::read(&mut std::io::Cursor::new(image)).unwrap();
+        // Virtual range claims more data than the Yaz0 header.
+        let entry = &entries[2];
+        entries[2] = Entry::from(entry.virt_start(), entry.virt_end() + 8, entry.phys_start(), entry.phys_end());
+        rom.table = Some(Table::from(testing::TABLE_ADDRESS, entries));
+        for &matching in &[true, false] {
+            let result = decompress(&rom, matching);
+            assert!(matches!(result, Err(Error::SizeMismatch { index: 2, expected: 0x48, actual: 0x40 })));
+        }
+    }
+
+    #[test]
+    fn decompress_skip_errors() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let mut rom = testing::build_rom(&[(&[1, 2, 3], false), (&data, true), (&data, true)]);
+        let offset = rom.entry(3).unwrap().phys_start() as usize;
+        rom.rom.full_mut()[offset..offset + 4].copy_from_slice(b"Bad0");
+        let options = Options { fill_byte: 0xFF, skip_errors: true, ..Options::default() };
+        for &matching in &[true, false] {
+            let (dec, failures) = decompress_with_failures(&rom, matching, &options).unwrap();
+            assert_eq!(failures, vec![3]);
+            assert_eq!(dec.extract(2).unwrap().unwrap(), vec![1, 2, 3]);
+            assert_eq!(dec.extract(3).unwrap().unwrap(), vec![0; 0x40]);
+            assert_eq!(dec.extract(4).unwrap().unwrap(), data);
+        }
+        // Without skipping, the first failure is returned.
+        let options = Options { skip_errors: false, ..options };
+        assert!(matches!(decompress_with_failures(&rom, true, &options), Err(Error::EntryDecompress { index: 3, .. })));
+    }
+
+    #[test]
+    fn decompress_max_output() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&[1, 2, 3], false), (&data, true)]);
+        let options = Options { max_output: Some(0x40), ..Options::default() };
+        assert!(decompress_with_options(&rom, false, &options).is_ok());
+
+        // Bomb claims a huge size in its header.
+        let mut rom = rom;
+        let offset = rom.entry(3).unwrap().phys_start() as usize;
+        rom.rom.full_mut()[offset + 4..offset + 8].copy_from_slice(&[0xFF, 0xFF, 0xFF, 0xF0]);
+        let options = Options { max_output: Some(0x1000), ..Options::default() };
+        match decompress_with_options(&rom, false, &options) {
+            Err(Error::EntryDecompress { index: 3, source: codec::Error::OutputLimitExceeded(size, 0x1000), .. }) => {
+                assert_eq!(size, 0xFFFF_FFF0);
+            }
+            _ => panic!("expected OutputLimitExceeded error"),
+        }
+        assert!(matches!(rom.extract_with_limit(3, 0x1000), Err(rom::Error::CodecError(_))));
+    }
+
+    #[test]
+    fn decompress_zero_length() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let files: [(&[u8], bool); 4] = [(&data, true), (&[], false), (&[1, 2, 3], false), (&[], false)];
+        let (mut image, mut entries) = testing::build_image(&files);
+        // Zero-length entries which are not empty: compressed with no data, and uncompressed past the end.
+        let virt = entries[3].virt_start();
+        entries[3] = Entry::from(virt, virt, 0x10D0, 0x10D0);
+        entries[5] = Entry::from_uncompressed(0x10_0000, 0x10_0000, 0x10_0000);
+        let mut slice = &mut image[testing::TABLE_ADDRESS as usize..];
+        for entry in &entries {
+            entry.write(&mut slice).unwrap();
+        }
+        let rom = Rom::read(&mut std::io::Cursor::new(image)).unwrap();
+
+        for &matching in &[true, false] {
+            let dec = decompress(&rom, matching).unwrap();
+            let table = dec.table.as_ref().unwrap();
+            assert!(table.entries[3].virt_eq(&rom.table.as_ref().unwrap().entries[3]));
+            assert_eq!(dec.extract(2).unwrap().unwrap(), data);
+            assert_eq!(dec.extract(3).unwrap().unwrap(), Vec::<u8>::new());
+            assert_eq!(dec.extract(4).unwrap().unwrap(), vec![1, 2, 3]);
+        }
+        assert_eq!(rom.extract(3).unwrap().unwrap(), Vec::<u8>::new());
+        assert_eq!(rom.extract(5).unwrap().unwrap(), Vec::<u8>::new());
+    }
 }
@@ -1,11 +1,12 @@
-use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
-use std::convert::TryInto;
+use byteorder::{BigEndian, ByteOrder, ReadBytesExt, WriteBytesExt};
+use std::convert::TryFrom;
 use std::fmt;
-use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
 use std::ops::Range;
 use thiserror::Error;
 
-use crate::util;
+use crate::rom::{self, Rom};
+use crate::util::{self, ConvertRangeExt};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -15,6 +16,18 @@ pub enum Error {
     InvalidHeader,
     #[error("Invalid mapping range")]
     InvalidRange(Mapping, Range<u32>),
+    #[error("Table of {0} bytes does not fit in {1} bytes")]
+    TableTooLarge(usize, usize),
+    #[error("Entry index out-of-range: {0}")]
+    IndexError(usize),
+    #[error("Table does not contain an entry for itself")]
+    NoSelfEntry,
+    #[error("Table entry for itself is not uncompressed: {0:?}")]
+    InvalidSelfEntry(EntryType),
+    #[error("Physical range (0x{:08X}, 0x{:08X}) exceeds rom size 0x{1:X}", .0.start, .0.end)]
+    RangeExceedsRom(Range<u32>, usize),
+    #[error("Table offset is not 16-byte aligned: 0x{0:08X}")]
+    UnalignedTable(u32),
 }
 
 /// Custom Result type.
@@ -38,7 +51,7 @@ impl fmt::Display for Mapping {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Entry {
     values: [u32; 4],
 }
@@ -119,10 +132,9 @@ impl Entry {
         let (virt, phys, _) = self.validate()?;
         match phys {
             Some(phys) => {
-                let vlen: isize = virt.len().try_into().unwrap();
-                let plen: isize = phys.len().try_into().unwrap();
-                let diff = Some(vlen - plen);
-                Ok(diff)
+                // Lengths fit in `u32`, so the difference cannot overflow an `i64`.
+                let diff = virt.len() as i64 - phys.len() as i64;
+                Ok(Some(diff as isize))
             }
             _ => Ok(None),
         }
@@ -134,6 +146,20 @@ impl Entry {
         }
     }
 
+    /// Create `Entry` from its 16 serialized bytes, as 4 big-endian words.
+    pub fn from_bytes(bytes: &[u8; 16]) -> Self {
+        let mut values = [0; 4];
+        BigEndian::read_u32_into(bytes, &mut values);
+        Self { values }
+    }
+
+    /// Serialize `Entry` to 16 bytes, as 4 big-endian words.
+    pub fn to_bytes(&self) -> [u8; 16] {
+        let mut bytes = [0; 16];
+        BigEndian::write_u32_into(&self.values, &mut bytes);
+        bytes
+    }
+
     /// Create `Entry` for uncompressed data.
     pub fn from_uncompressed(virt_start: u32, virt_end: u32, phys_start: u32) -> Self {
         Self::from(virt_start, virt_end, phys_start, 0)
@@ -151,9 +177,9 @@ impl Entry {
     /// Get the respective EntryType.
     pub fn kind(&self) -> EntryType {
         let phys = self.phys();
-        if self.as_ref().into_iter().all(|&x| x == 0) {
+        if self.as_ref().iter().all(|&x| x == 0) {
             EntryType::Empty
-        } else if phys.start == ::std::u32::MAX && phys.end == ::std::u32::MAX {
+        } else if phys.start == u32::MAX && phys.end == u32::MAX {
             EntryType::DoesNotExist
         } else if phys.end == 0 {
             EntryType::Decompressed
@@ -162,6 +188,26 @@ impl Entry {
         }
     }
 
+    /// Whether or not the entry file is Yaz0 compressed.
+    pub fn is_compressed(&self) -> bool {
+        matches!(self.kind(), EntryType::Compressed)
+    }
+
+    /// Whether or not the entry file is stored without compression.
+    pub fn is_decompressed(&self) -> bool {
+        matches!(self.kind(), EntryType::Decompressed)
+    }
+
+    /// Whether or not the entry is empty (all fields are `0`).
+    pub fn is_empty(&self) -> bool {
+        matches!(self.kind(), EntryType::Empty)
+    }
+
+    /// Whether or not the entry file exists in the rom, either compressed or not.
+    pub fn exists(&self) -> bool {
+        matches!(self.kind(), EntryType::Compressed | EntryType::Decompressed)
+    }
+
     pub fn read<T: Read>(reader: &mut T) -> io::Result<Self> {
         let virt_start = reader.read_u32::<BigEndian>()?;
         let virt_end = reader.read_u32::<BigEndian>()?;
@@ -181,6 +227,11 @@ impl Entry {
         self.virt_start()..self.virt_end()
     }
 
+    /// Whether or not both entries have the same virtual range, regardless of physical addresses or compression.
+    pub fn virt_eq(&self, other: &Entry) -> bool {
+        self.virt() == other.virt()
+    }
+
     /// Get the "real" address `Range` of file data relative to ROM start.
     pub fn range(&self) -> (Option<Range<u32>>, EntryType) {
         let kind = self.kind();
@@ -188,26 +239,32 @@ impl Entry {
             EntryType::Compressed => (Some(self.phys()), kind),
             EntryType::Decompressed => {
                 // If decompressed, physical mapping end will be 0, thus use virtual mapping range length.
+                // Wraps on overflow so that `validate` reports the range as invalid.
                 let length = self.virt().len() as u32;
-                let range = self.phys_start()..self.phys_start() + length;
+                let range = self.phys_start()..self.phys_start().wrapping_add(length);
                 (Some(range), kind)
             }
             _ => (None, kind),
         }
     }
 
+    /// Get the length of the Yaz0 data on disk, if the entry file is compressed.
+    pub fn compressed_len(&self) -> Option<u32> {
+        match self.kind() {
+            EntryType::Compressed => Some(self.phys_end().wrapping_sub(self.phys_start())),
+            _ => None,
+        }
+    }
+
+    /// Get the length of the entry file once decompressed (virtual length).
+    pub fn decompressed_len(&self) -> u32 {
+        self.virt_end().wrapping_sub(self.virt_start())
+    }
+
     /// Wrapper for `range` function to get range values as `usize`.
     pub fn range_usize(&self) -> (Option<Range<usize>>, EntryType) {
         let (range, kind) = self.range();
-        match range {
-            Some(range) => {
-                let start: usize = (range.start as usize).try_into().unwrap();
-                let end: usize = (range.end as usize).try_into().unwrap();
-                let result = Range { start, end };
-                (Some(result), kind)
-            }
-            _ => (None, kind)
-        }
+        (range.map(|range| range.to_usize()), kind)
     }
 
     /// Validate this table entry.
@@ -231,6 +288,15 @@ impl Entry {
         }
     }
 
+    /// Validate this table entry, additionally checking that its physical range lies within a rom of `rom_len` bytes.
+    pub fn validate_against(&self, rom_len: usize) -> Result<(Range<u32>, Option<Range<u32>>, EntryType)> {
+        let (virt, phys, kind) = self.validate()?;
+        match phys {
+            Some(phys) if phys.end as usize > rom_len => Err(Error::RangeExceedsRom(phys, rom_len)),
+            phys => Ok((virt, phys, kind)),
+        }
+    }
+
     /// Write.
     pub fn write<T: Write>(&self, writer: &mut T) -> io::Result<usize> {
         writer.write_u32::<BigEndian>(self.virt_start())?;
@@ -241,6 +307,8 @@ impl Entry {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
 pub enum EntryType {
     /// Entry file is Yaz0 compressed.
     Compressed,
@@ -252,6 +320,97 @@ pub enum EntryType {
     Empty,
 }
 
+/// Planned physical layout of files after a rebuild.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RebuildPlan {
+    /// New physical range of each entry's file, if it has one.
+    ///
+    /// Values are 64-bit so that planning changes which would not fit in a rom cannot overflow.
+    pub ranges: Vec<Option<Range<u64>>>,
+    /// Resulting rom size in bytes.
+    pub size: u64,
+}
+
+impl RebuildPlan {
+    /// Whether or not the resulting rom would be larger than the target size.
+    pub fn exceeds(&self, target: u64) -> bool {
+        self.size > target
+    }
+}
+
+/// Mapping of a single file from its physical range in the rom to its virtual range.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AddressMapping {
+    /// Physical range of file data in the rom.
+    pub phys: Range<u32>,
+    /// Virtual range of the file once loaded (and decompressed).
+    pub virt: Range<u32>,
+    /// Whether or not file data is compressed.
+    pub compressed: bool,
+}
+
+/// Map from physical rom addresses to virtual addresses, built from the entries of a `Table`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct AddressMap {
+    /// File mappings, sorted by physical start address.
+    pub mappings: Vec<AddressMapping>,
+}
+
+impl AddressMap {
+    /// Get the mapping of the file containing the physical address, if any.
+    pub fn find(&self, phys_addr: u32) -> Option<&AddressMapping> {
+        let index = self.mappings.partition_point(|mapping| mapping.phys.start <= phys_addr);
+        index.checked_sub(1)
+            .map(|index| &self.mappings[index])
+            .filter(|mapping| mapping.phys.contains(&phys_addr))
+    }
+
+    /// Translate a physical address to its virtual address.
+    ///
+    /// Within uncompressed files the offset from the file start is preserved. Offsets into compressed data have no
+    /// corresponding virtual offset, so any address within a compressed file translates to its virtual start.
+    pub fn translate(&self, phys_addr: u32) -> Option<u32> {
+        self.find(phys_addr).map(|mapping| match mapping.compressed {
+            true => mapping.virt.start,
+            false => mapping.virt.start + (phys_addr - mapping.phys.start),
+        })
+    }
+}
+
+/// Summary of the entries of a `Table`, see `Table::report`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TableReport {
+    /// Number of compressed entries.
+    pub compressed: usize,
+    /// Number of uncompressed entries.
+    pub decompressed: usize,
+    /// Number of entries whose file does not exist.
+    pub does_not_exist: usize,
+    /// Number of empty entries.
+    pub empty: usize,
+    /// Number of entries with invalid ranges, which are not included in byte totals.
+    pub invalid: usize,
+    /// Total physical size of all files in the rom.
+    pub physical_bytes: u64,
+    /// Total virtual size of all files once decompressed.
+    pub virtual_bytes: u64,
+    /// Number of compressed files which are not smaller than their decompressed size.
+    pub unhelpful: usize,
+}
+
+impl fmt::Display for TableReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Entries: {} compressed, {} decompressed, {} missing, {} empty",
+            self.compressed, self.decompressed, self.does_not_exist, self.empty)?;
+        if self.invalid != 0 {
+            writeln!(f, "Invalid Entries: {}", self.invalid)?;
+        }
+        writeln!(f, "Physical Size: 0x{:X}", self.physical_bytes)?;
+        writeln!(f, "Virtual Size: 0x{:X}", self.virtual_bytes)?;
+        write!(f, "Compressed Files Not Smaller: {}", self.unhelpful)
+    }
+}
+
 pub struct Table {
     /// Virtual address of `dmadata` file.
     pub address: u32,
@@ -268,7 +427,42 @@ impl fmt::Display for Table {
     }
 }
 
+/// Display wrapper for a `Table` which colors entries by `EntryType`, for terminal output.
+#[cfg(feature = "color")]
+pub struct ColoredTable<'t>(&'t Table);
+
+#[cfg(feature = "color")]
+impl fmt::Display for ColoredTable<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use owo_colors::OwoColorize;
+        for entry in &self.0.entries {
+            match entry.kind() {
+                EntryType::Compressed => writeln!(f, "{}", entry.yellow())?,
+                EntryType::Decompressed => writeln!(f, "{}", entry.green())?,
+                EntryType::DoesNotExist => writeln!(f, "{}", entry.red())?,
+                EntryType::Empty => writeln!(f, "{}", entry.bright_black())?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl Table {
+    /// Create a blank `Table` at the given address, with room for `capacity` entries.
+    ///
+    /// The table contains the expected initial entry and an uncompressed entry for itself, with all other entries
+    /// `Empty`. The capacity is at least 2 to hold these entries.
+    ///
+    /// Fails with `Error::TableTooLarge` if the table would not fit in the address space after its address.
+    pub fn new(address: u32, capacity: usize) -> Result<Self> {
+        let capacity = capacity.max(2);
+        let end = Self::end_address(address, capacity)?;
+        let mut entries = vec![Entry::default(); capacity];
+        entries[0] = Entry::initial();
+        entries[1] = Entry::from_uncompressed(address, end, address);
+        Ok(Self::from(address, entries))
+    }
+
     pub fn from(address: u32, entries: Vec<Entry>) -> Self {
         Self {
             address,
@@ -276,6 +470,20 @@ impl Table {
         }
     }
 
+    /// Get a displayable form of the `Table` with entries colored by `EntryType`.
+    ///
+    /// The plain `Display` implementation is uncolored, and should be preferred when output is not a terminal.
+    #[cfg(feature = "color")]
+    pub fn display_colored(&self) -> ColoredTable<'_> {
+        ColoredTable(self)
+    }
+
+    /// Find and read `Table` from ROM bytes.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Option<Table>> {
+        let table = Self::find(&mut Cursor::new(bytes))?;
+        Ok(table.map(|(table, _)| table))
+    }
+
     /// Find `Table` in ROM and return along with offset.
     pub fn find<T: Read + Seek>(mut stream: &mut T) -> Result<Option<(Table, usize)>> {
         let offset = Self::find_offset(stream)?;
@@ -283,50 +491,249 @@ impl Table {
             Some(offset) => {
                 stream.seek(SeekFrom::Start(offset))?;
                 let table = Self::read(&mut stream)?;
-                let origin: usize = (offset as usize).try_into().unwrap();
+                let origin: usize = offset as usize;
                 Ok(Some((table, origin)))
             }
             None => Ok(None),
         }
     }
 
+    /// Find all `Table` structures in ROM, each along with its offset.
+    ///
+    /// Scanning continues after the end of each table found. Matches of the initial entry which are not followed by a
+    /// complete table are skipped.
+    pub fn find_all<T: Read + Seek>(mut stream: &mut T) -> Result<Vec<(Table, usize)>> {
+        let mut tables = Vec::new();
+        let mut start = 0;
+        while let Some(offset) = Self::find_offset_from(stream, start)? {
+            stream.seek(SeekFrom::Start(offset))?;
+            match Self::read(&mut stream) {
+                Ok(table) => {
+                    start = offset + table.size() as u64;
+                    tables.push((table, offset as usize));
+                }
+                Err(Error::IOError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    start = offset + Entry::SIZE as u64;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(tables)
+    }
+
     /// Read `Table` from reader at given offset. Assumes the reader is already positioned at this offset.
+    ///
+    /// Tables are always 16-byte aligned, so an unaligned offset is rejected as a false-positive match.
     pub fn read_at<T: Read>(mut reader: &mut T, begin: u32) -> Result<Table> {
+        if !begin.is_multiple_of(0x10) {
+            return Err(Error::UnalignedTable(begin));
+        }
         let mut current = begin;
-        let mut dmadata = None;
+        let mut dmadata: Option<Range<u32>> = None;
         let mut entries = Vec::new();
         loop {
+            // Check if the end has been reached before reading past it.
+            if let Some(dmadata) = &dmadata {
+                if current >= dmadata.end {
+                    break;
+                }
+            }
+
             let entry = Entry::read(&mut reader)?;
 
             // Table should include an entry about itself, it should be uncompressed.
-            if dmadata == None && entry.virt_start() == begin {
+            if dmadata.is_none() && entry.virt_start() == begin {
                 dmadata = Some(entry.virt());
             }
 
-            // Check if the end has been reached.
-            match &dmadata {
-                Some(dmadata) => {
-                    if current >= dmadata.end {
-                        break;
-                    }
-                }
-                _ => (),
-            }
-
             entries.push(entry);
-            current += Entry::SIZE as u32;
+            current = match current.checked_add(Entry::SIZE as u32) {
+                Some(current) => current,
+                None => break,
+            };
         }
-        let table = Table::from(dmadata.unwrap().start, entries);
+        // The loop only ends after the table entry has been found, or at the end of the address space.
+        let address = dmadata.map_or(begin, |dmadata| dmadata.start);
+        let table = Table::from(address, entries);
         Ok(table)
     }
 
+    /// Read `Table` at a known offset into the decompressed file of an entry, such as the `dmadata` symbol of a
+    /// decompiled build within its code file.
+    ///
+    /// Unlike `find`, this does not scan for the initial entry, so it cannot match a false-positive table. The table
+    /// address is the virtual address of the offset, so the table must contain an entry for itself at that address.
+    pub fn read_from_code(
+        rom: &Rom,
+        code_index: usize,
+        offset_in_file: usize,
+    ) -> std::result::Result<Table, rom::Error> {
+        let file = rom.extract(code_index)?.ok_or(rom::Error::NoFileData(code_index))?;
+        let mut reader = file.get(offset_in_file..).unwrap_or_default();
+        // Decompressed files are no larger than their virtual range, so the offset fits in a `u32` once clamped.
+        let offset = offset_in_file.min(file.len()) as u32;
+        let begin = rom.entry(code_index)?.virt_start().checked_add(offset)
+            .ok_or(rom::Error::TableOutOfRange(offset_in_file))?;
+        Ok(Self::read_at(&mut reader, begin)?)
+    }
+
     /// Read `Table` from stream.
     pub fn read<T: Read + Seek>(mut stream: &mut T) -> Result<Table> {
-        let offset = stream.seek(SeekFrom::Current(0))?;
-        let begin = (offset as u32).try_into().unwrap();
+        let offset = stream.stream_position()?;
+        let begin = offset as u32;
         Self::read_at(&mut stream, begin)
     }
 
+    /// Get a fingerprint of the `Table` layout, hashing the words of all entries with 64-bit FNV-1a.
+    ///
+    /// The result is stable across runs, and tables with the same entries have the same fingerprint.
+    pub fn fingerprint(&self) -> u64 {
+        const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+        const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+        self.entries
+            .iter()
+            .flat_map(|entry| entry.values.iter().flat_map(|value| value.to_be_bytes()))
+            .fold(FNV_OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    }
+
+    /// Plan the physical layout of files after replacing some of them, given as `(index, new length)` changes.
+    ///
+    /// Files are packed in table order with 16-byte alignment, so each change shifts all files after it. Changed files
+    /// are assumed to be stored uncompressed, and entries without file data gain one if changed.
+    pub fn plan_rebuild(&self, changes: &[(usize, u32)]) -> Result<RebuildPlan> {
+        let mut lengths: Vec<Option<u32>> = self.entries.iter()
+            .map(|entry| entry.range().0.map(|range| range.len() as u32))
+            .collect();
+        for &(index, length) in changes {
+            *lengths.get_mut(index).ok_or(Error::IndexError(index))? = Some(length);
+        }
+        let mut offset: u64 = 0;
+        let ranges = lengths.into_iter().map(|length| {
+            length.map(|length| {
+                let range = offset..offset + length as u64;
+                offset = (range.end + 0xF) & !0xF;
+                range
+            })
+        }).collect();
+        Ok(RebuildPlan { ranges, size: offset })
+    }
+
+    /// Build an `AddressMap` of all entries with valid, non-empty file data.
+    pub fn build_address_map(&self) -> AddressMap {
+        let mut mappings: Vec<AddressMapping> = self.entries.iter()
+            .filter_map(|entry| match entry.validate() {
+                Ok((virt, Some(phys), kind)) if !phys.is_empty() => Some(AddressMapping {
+                    phys,
+                    virt,
+                    compressed: kind == EntryType::Compressed,
+                }),
+                _ => None,
+            })
+            .collect();
+        mappings.sort_by_key(|mapping| mapping.phys.start);
+        AddressMap { mappings }
+    }
+
+    /// Get entries with file data paired with their table indexes, sorted by physical start address.
+    ///
+    /// Entries which do not exist or are empty are skipped. Entries sharing a physical start keep their table order.
+    pub fn entries_by_phys(&self) -> Vec<(usize, &Entry)> {
+        let mut entries: Vec<(usize, &Entry)> = self.entries.iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.range().0.is_some())
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.phys_start());
+        entries
+    }
+
+    /// Summarize entry types and file sizes of all entries.
+    pub fn report(&self) -> TableReport {
+        let mut report = TableReport::default();
+        for entry in &self.entries {
+            let kind = entry.kind();
+            match kind {
+                EntryType::Compressed => report.compressed += 1,
+                EntryType::Decompressed => report.decompressed += 1,
+                EntryType::DoesNotExist => report.does_not_exist += 1,
+                EntryType::Empty => report.empty += 1,
+            }
+            match entry.validate() {
+                Ok((virt, Some(phys), _)) => {
+                    report.physical_bytes += phys.len() as u64;
+                    report.virtual_bytes += virt.len() as u64;
+                    if kind == EntryType::Compressed && phys.len() >= virt.len() {
+                        report.unhelpful += 1;
+                    }
+                }
+                Ok(_) => {}
+                Err(_) => report.invalid += 1,
+            }
+        }
+        report
+    }
+
+    /// Get the entry describing the `Table` itself, whose virtual start is the table address.
+    pub fn self_entry(&self) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.virt_start() == self.address)
+    }
+
+    /// Verify that the `Table` contains an uncompressed entry for itself.
+    ///
+    /// Any other entry type indicates a corrupt or misidentified table.
+    pub fn verify(&self) -> Result<()> {
+        let entry = self.self_entry().ok_or(Error::NoSelfEntry)?;
+        match entry.kind() {
+            EntryType::Decompressed => Ok(()),
+            kind => Err(Error::InvalidSelfEntry(kind)),
+        }
+    }
+
+    /// Append `additional` `Empty` entries, growing the entry of the `Table` itself to cover them.
+    ///
+    /// The table grows in place, so the space after it in the rom must not be used by another file. Otherwise use
+    /// `Rom::relocate_table` or `Rom::rebuild` afterwards.
+    ///
+    /// Fails with `Error::TableTooLarge` if the grown table would not fit in the address space after its address.
+    pub fn reserve(&mut self, additional: usize) -> Result<()> {
+        self.verify()?;
+        let count = self.entries.len().checked_add(additional)
+            .ok_or(Error::TableTooLarge(usize::MAX, Self::max_size(self.address)))?;
+        let end = Self::end_address(self.address, count)?;
+        self.entries.resize(count, Entry::default());
+        let address = self.address;
+        let entry = self.entries.iter_mut().find(|entry| entry.virt_start() == address).unwrap();
+        if entry.virt_end() < end {
+            *entry = Entry::from_uncompressed(address, end, entry.phys_start());
+        }
+        Ok(())
+    }
+
+    /// Recompute the virtual end of the entry of the `Table` itself from the current entry count.
+    ///
+    /// Unlike `reserve`, this also shrinks the entry when entries were removed from `entries` directly. Fails with
+    /// `Error::TableTooLarge` if the table would not fit in the address space after its address.
+    pub fn refresh_self_entry(&mut self) -> Result<()> {
+        self.verify()?;
+        let end = Self::end_address(self.address, self.entries.len())?;
+        let address = self.address;
+        let entry = self.entries.iter_mut().find(|entry| entry.virt_start() == address).unwrap();
+        *entry = Entry::from_uncompressed(address, end, entry.phys_start());
+        Ok(())
+    }
+
+    /// Get the amount of bytes available for a `Table` at the given address, such that its end address fits in 32 bits.
+    fn max_size(address: u32) -> usize {
+        (u32::MAX - address) as usize
+    }
+
+    /// Get the virtual end address of a `Table` at the given address with `count` entries.
+    fn end_address(address: u32, count: usize) -> Result<u32> {
+        let size = count.saturating_mul(Entry::SIZE);
+        u32::try_from(size).ok()
+            .and_then(|size| address.checked_add(size))
+            .ok_or(Error::TableTooLarge(size, Self::max_size(address)))
+    }
+
     /// Get size of `Table` in bytes.
     pub fn size(&self) -> usize {
         self.entries.len() * Entry::SIZE
@@ -334,24 +741,367 @@ impl Table {
 
     /// Find the offset of the DMA table, relative to start of stream.
     pub fn find_offset<T: Read + Seek>(stream: &mut T) -> Result<Option<u64>> {
+        Self::find_offset_from(stream, 0)
+    }
+
+    /// Find the offset of the next DMA table at or after the given offset, relative to start of stream.
+    fn find_offset_from<T: Read + Seek>(stream: &mut T, start: u64) -> Result<Option<u64>> {
         let initial = Entry::initial();
-        stream.seek(SeekFrom::Start(0))?;
+        stream.seek(SeekFrom::Start(start))?;
         loop {
-            let entry = Entry::read(stream)?;
+            // Reaching the end of the stream means there is no table.
+            let entry = match Entry::read(stream) {
+                Ok(entry) => entry,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(err) => return Err(err.into()),
+            };
             if entry == initial {
-                let delta: u64 = (Entry::SIZE as u64).try_into().unwrap();
-                let result = stream.seek(SeekFrom::Current(0))? - delta;
+                let delta = Entry::SIZE as u64;
+                let result = stream.stream_position()? - delta;
                 return Ok(Some(result))
             }
         }
     }
 
     /// Write `Table` entries to writer.
+    ///
+    /// If entries were added to or removed from `entries` directly, call `refresh_self_entry` first so the entry of
+    /// the `Table` itself matches its size.
     pub fn write<T: Write>(&self, mut writer: &mut T) -> Result<usize> {
         let mut length = 0;
         for entry in &self.entries {
-            length = length + entry.write(&mut writer)?;
+            length += entry.write(&mut writer)?;
         }
         Ok(length)
     }
+
+    /// Serialize `Table` entries to a new `Vec`.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buffer = Vec::with_capacity(self.size());
+        // Writing to a `Vec` cannot fail.
+        self.write(&mut buffer).unwrap();
+        buffer
+    }
+
+    /// Write `Table` entries directly into the start of a slice, such as the image at the table offset.
+    pub fn write_into(&self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.size();
+        if buf.len() < size {
+            return Err(Error::TableTooLarge(size, buf.len()));
+        }
+        for (entry, chunk) in self.entries.iter().zip(buf.chunks_exact_mut(Entry::SIZE)) {
+            BigEndian::write_u32_into(&entry.values, chunk);
+        }
+        Ok(size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_new() {
+        let table = Table::new(0x1060, 4).unwrap();
+        assert_eq!(table.size(), 0x40);
+        assert_eq!(table.entries[0], Entry::initial());
+        assert_eq!(table.entries[1], Entry::from_uncompressed(0x1060, 0x10A0, 0x1060));
+        assert!(table.entries[1].is_decompressed());
+        assert!(table.entries[2].is_empty());
+        assert!(table.entries[3].is_empty());
+
+        // Written table should be readable.
+        let mut buffer = Vec::new();
+        table.write(&mut buffer).unwrap();
+        let read = Table::read_at(&mut Cursor::new(buffer), 0x1060).unwrap();
+        assert_eq!(read.address, table.address);
+        assert_eq!(read.entries, table.entries);
+
+        // Tables must end within the address space.
+        assert!(matches!(Table::new(0xFFFF_FFE0, 2), Err(Error::TableTooLarge(0x20, 0x1F))));
+        assert!(matches!(Table::new(0x1060, usize::MAX), Err(Error::TableTooLarge(usize::MAX, _))));
+        assert_eq!(Table::new(0xFFFF_FFD0, 2).unwrap().entries[1].virt_end(), 0xFFFF_FFF0);
+    }
+
+    #[test]
+    fn table_write_into() {
+        let table = Table::new(0x1060, 3).unwrap();
+        let mut buffer = Vec::new();
+        table.write(&mut buffer).unwrap();
+        let mut slice = vec![0xFF; 0x34];
+        assert_eq!(table.write_into(&mut slice).unwrap(), 0x30);
+        assert_eq!(&slice[..0x30], &buffer[..]);
+        assert_eq!(table.to_vec(), buffer);
+        assert!(slice[0x30..].iter().all(|&b| b == 0xFF));
+        assert!(matches!(table.write_into(&mut slice[..0x2F]), Err(Error::TableTooLarge(0x30, 0x2F))));
+    }
+
+    #[test]
+    fn table_from_bytes() {
+        let table = Table::new(0x20, 3).unwrap();
+        let mut bytes = vec![0xFF; 0x20];
+        table.write(&mut bytes).unwrap();
+        let read = Table::from_bytes(&bytes).unwrap().unwrap();
+        assert_eq!(read.address, 0x20);
+        assert_eq!(read.entries, table.entries);
+        assert!(Table::from_bytes(&bytes[..0x2F]).unwrap().is_none());
+        // Table entry is missing from truncated data.
+        assert!(Table::from_bytes(&bytes[..0x3F]).is_err());
+    }
+
+    #[test]
+    fn entry_bytes() {
+        let entry = Entry::from(0x1000, 0x1800, 0x2000, 0x2300);
+        let bytes = entry.to_bytes();
+        assert_eq!(&bytes[..8], &[0, 0, 0x10, 0, 0, 0, 0x18, 0]);
+        let mut written = Vec::new();
+        entry.write(&mut written).unwrap();
+        assert_eq!(&written[..], &bytes[..]);
+        assert_eq!(Entry::from_bytes(&bytes), entry);
+    }
+
+    #[test]
+    fn entry_lengths() {
+        let compressed = Entry::from(0x1000, 0x1800, 0x2000, 0x2300);
+        assert_eq!(compressed.compressed_len(), Some(0x300));
+        assert_eq!(compressed.decompressed_len(), 0x800);
+        let decompressed = Entry::from_uncompressed(0x1000, 0x1800, 0x1000);
+        assert_eq!(decompressed.compressed_len(), None);
+        assert_eq!(decompressed.decompressed_len(), 0x800);
+        assert_eq!(Entry::default().compressed_len(), None);
+    }
+
+    #[test]
+    fn entry_validate_against() {
+        let entry = Entry::from(0x1000, 0x1800, 0x2000, 0x2300);
+        assert!(entry.validate_against(0x2300).is_ok());
+        assert!(matches!(entry.validate_against(0x22FF), Err(Error::RangeExceedsRom(_, 0x22FF))));
+        assert!(Entry::default().validate_against(0).is_ok());
+    }
+
+    #[test]
+    fn entry_validate_overflow() {
+        let entry = Entry::from(0, 0x20, 0xFFFF_FFF0, 0);
+        assert!(matches!(entry.validate(), Err(Error::InvalidRange(Mapping::Physical, _))));
+        assert!(entry.diff().is_err());
+    }
+
+    #[test]
+    fn table_fingerprint() {
+        // FNV-1a of no data is the offset basis.
+        assert_eq!(Table::from(0, Vec::new()).fingerprint(), 0xcbf2_9ce4_8422_2325);
+        let a = Table::new(0x1060, 3).unwrap();
+        let mut b = Table::new(0x1060, 3).unwrap();
+        assert_eq!(a.fingerprint(), 0xdaed_a032_7164_b6b5);
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        b.entries[2] = Entry::from_uncompressed(0x1090, 0x10A0, 0x1090);
+        assert_ne!(a.fingerprint(), b.fingerprint());
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn table_display_colored() {
+        let table = Table::new(0x1060, 3).unwrap();
+        let colored = table.display_colored().to_string();
+        let lines: Vec<&str> = colored.lines().collect();
+        assert_eq!(lines.len(), 3);
+        // Each line wraps the plain entry text in color codes.
+        for (line, entry) in lines.iter().zip(&table.entries) {
+            assert!(line.starts_with("\x1b["));
+            assert!(line.contains(&entry.to_string()));
+        }
+        assert_ne!(lines[0], lines[2]);
+    }
+
+    #[test]
+    fn table_find_all() {
+        let mut bytes = vec![0xFF; 0x20];
+        Table::new(0x20, 3).unwrap().write(&mut bytes).unwrap();
+        bytes.resize(0x60, 0xFF);
+        Table::new(0x60, 2).unwrap().write(&mut bytes).unwrap();
+        // Initial entry without a table following it.
+        Entry::initial().write(&mut bytes).unwrap();
+        let tables = Table::find_all(&mut Cursor::new(&bytes)).unwrap();
+        assert_eq!(tables.len(), 2);
+        assert_eq!(tables[0].1, 0x20);
+        assert_eq!(tables[0].0.entries.len(), 3);
+        assert_eq!(tables[1].1, 0x60);
+        assert_eq!(tables[1].0.entries.len(), 2);
+    }
+
+    #[test]
+    fn table_plan_rebuild() {
+        let mut table = Table::new(0x1060, 5).unwrap();
+        table.entries[2] = Entry::from(0x1090, 0x10B0, 0x1090, 0x10A0);
+        table.entries[3] = Entry::from_uncompressed(0x10B0, 0x10C8, 0x10A0);
+        // Head and table entries come first.
+        let plan = table.plan_rebuild(&[]).unwrap();
+        assert_eq!(plan.ranges[1], Some(0x1060..0x10B0));
+        assert_eq!(plan.ranges[2], Some(0x10B0..0x10C0));
+        assert_eq!(plan.ranges[3], Some(0x10C0..0x10D8));
+        assert_eq!(plan.ranges[4], None);
+        assert_eq!(plan.size, 0x10E0);
+
+        let plan = table.plan_rebuild(&[(2, 0x21), (4, 8)]).unwrap();
+        assert_eq!(plan.ranges[2], Some(0x10B0..0x10D1));
+        assert_eq!(plan.ranges[3], Some(0x10E0..0x10F8));
+        assert_eq!(plan.ranges[4], Some(0x1100..0x1108));
+        assert_eq!(plan.size, 0x1110);
+        assert!(plan.exceeds(0x1100));
+        assert!(!plan.exceeds(0x1110));
+        assert!(matches!(table.plan_rebuild(&[(5, 0)]), Err(Error::IndexError(5))));
+    }
+
+    #[test]
+    fn table_verify() {
+        let mut table = Table::new(0x1060, 3).unwrap();
+        assert_eq!(table.self_entry(), Some(&table.entries[1]));
+        assert!(table.verify().is_ok());
+        table.entries[1] = Entry::from(0x1060, 0x1090, 0x1060, 0x1080);
+        assert!(matches!(table.verify(), Err(Error::InvalidSelfEntry(EntryType::Compressed))));
+        table.address = 0x2000;
+        assert!(table.self_entry().is_none());
+        assert!(matches!(table.verify(), Err(Error::NoSelfEntry)));
+    }
+
+    #[test]
+    fn table_reserve() {
+        let mut table = Table::new(0x1060, 3).unwrap();
+        table.reserve(2).unwrap();
+        assert_eq!(table.entries.len(), 5);
+        assert!(table.entries[3].is_empty() && table.entries[4].is_empty());
+        assert_eq!(table.entries[1], Entry::from_uncompressed(0x1060, 0x10B0, 0x1060));
+        assert!(table.verify().is_ok());
+        // Entries already covered by a larger self entry do not change it.
+        table.entries[1] = Entry::from_uncompressed(0x1060, 0x1100, 0x1060);
+        table.reserve(1).unwrap();
+        assert_eq!(table.entries[1].virt_end(), 0x1100);
+        table.address = 0x2000;
+        assert!(matches!(table.reserve(1), Err(Error::NoSelfEntry)));
+
+        // Tables cannot grow past the end of the address space.
+        let mut table = Table::new(0xFFFF_FF00, 3).unwrap();
+        assert!(matches!(table.reserve(0xD), Err(Error::TableTooLarge(0x100, 0xFF))));
+        assert!(matches!(table.reserve(usize::MAX), Err(Error::TableTooLarge(_, 0xFF))));
+        assert_eq!(table.entries.len(), 3);
+        table.reserve(0xC).unwrap();
+        assert_eq!(table.entries[1].virt_end(), 0xFFFF_FFF0);
+    }
+
+    #[test]
+    fn table_refresh_self_entry() {
+        let mut table = Table::new(0x1060, 3).unwrap();
+        table.entries.push(Entry::default());
+        table.refresh_self_entry().unwrap();
+        assert_eq!(table.entries[1], Entry::from_uncompressed(0x1060, 0x10A0, 0x1060));
+        table.entries.truncate(2);
+        table.refresh_self_entry().unwrap();
+        assert_eq!(table.entries[1], Entry::from_uncompressed(0x1060, 0x1080, 0x1060));
+        table.entries[1] = Entry::from(0x1060, 0x1080, 0x1060, 0x1070);
+        assert!(matches!(table.refresh_self_entry(), Err(Error::InvalidSelfEntry(_))));
+
+        let mut table = Table::new(0xFFFF_FF00, 3).unwrap();
+        table.entries.resize(0x10, Entry::default());
+        assert!(matches!(table.refresh_self_entry(), Err(Error::TableTooLarge(0x100, 0xFF))));
+        assert_eq!(table.entries[1].virt_end(), 0xFFFF_FF30);
+    }
+
+    #[test]
+    fn table_unaligned() {
+        let table = Table::new(0x1060, 3).unwrap();
+        let mut bytes = vec![0; 0x1068];
+        table.write(&mut bytes).unwrap();
+        let mut cursor = Cursor::new(&bytes);
+        cursor.set_position(0x1068);
+        assert!(matches!(Table::read(&mut cursor), Err(Error::UnalignedTable(0x1068))));
+        assert!(matches!(Table::read_at(&mut &bytes[0x1068..], 0x1068), Err(Error::UnalignedTable(0x1068))));
+    }
+
+    #[test]
+    fn address_map() {
+        let table = Table::from(0x1060, vec![
+            Entry::initial(),
+            Entry::from_uncompressed(0x1060, 0x1090, 0x1060),
+            Entry::from(0x2000, 0x3000, 0x1090, 0x1400),
+            Entry::from_uncompressed(0x3000, 0x3100, 0x1400),
+            Entry::from(0x4000, 0x4100, 0xFFFFFFFF, 0xFFFFFFFF),
+            Entry::default(),
+        ]);
+        let map = table.build_address_map();
+        assert_eq!(map.mappings.len(), 4);
+        assert_eq!(map.translate(0x1070), Some(0x1070));
+        assert_eq!(map.translate(0x1090), Some(0x2000));
+        assert_eq!(map.translate(0x13FF), Some(0x2000));
+        assert_eq!(map.translate(0x1410), Some(0x3010));
+        assert_eq!(map.translate(0x1500), None);
+        assert!(map.find(0x1200).unwrap().compressed);
+    }
+
+    #[test]
+    fn table_entries_by_phys() {
+        let table = Table::from(0x1060, vec![
+            Entry::initial(),
+            Entry::from_uncompressed(0x1060, 0x10B0, 0x1060),
+            Entry::from(0x2000, 0x3000, 0x1400, 0x1500),
+            Entry::from_uncompressed(0x3000, 0x3100, 0x10B0),
+            Entry::from(0x4000, 0x4100, 0xFFFFFFFF, 0xFFFFFFFF),
+            Entry::default(),
+        ]);
+        let indexes: Vec<usize> = table.entries_by_phys().iter().map(|&(index, _)| index).collect();
+        assert_eq!(indexes, vec![0, 1, 3, 2]);
+        assert_eq!(table.entries_by_phys()[2].1, &table.entries[3]);
+    }
+
+    #[test]
+    fn table_read_from_code() {
+        // The code file is the first file, at virtual address 0x1090 after the 3-entry table.
+        let embedded = Table::from(0x10B0, vec![
+            Entry::initial(),
+            Entry::from_uncompressed(0x10B0, 0x10D0, 0x10B0),
+        ]);
+        let code = [vec![0xAA; 0x20], embedded.to_vec(), vec![0xAA; 0x10]].concat();
+        let rom = crate::testing::build_rom(&[(&code, true)]);
+        let table = Table::read_from_code(&rom, 2, 0x20).unwrap();
+        assert_eq!(table.address, 0x10B0);
+        assert_eq!(table.entries, embedded.entries);
+
+        let result = Table::read_from_code(&rom, 2, 0x28);
+        assert!(matches!(result, Err(rom::Error::DMAError(Error::UnalignedTable(0x10B8)))));
+        let result = Table::read_from_code(&rom, 2, 0x100);
+        assert!(matches!(result, Err(rom::Error::DMAError(Error::IOError(_)))));
+        assert!(matches!(Table::read_from_code(&rom, 3, 0), Err(rom::Error::IndexError(3))));
+    }
+
+    #[test]
+    fn entry_virt_eq() {
+        let compressed = Entry::from(0x2000, 0x3000, 0x1090, 0x1400);
+        let decompressed = Entry::from_uncompressed(0x2000, 0x3000, 0x2000);
+        assert!(compressed.virt_eq(&decompressed));
+        assert!(compressed != decompressed);
+        assert!(!compressed.virt_eq(&Entry::from_uncompressed(0x2000, 0x3010, 0x2000)));
+    }
+
+    #[test]
+    fn table_report() {
+        let table = Table::from(0x1060, vec![
+            Entry::initial(),
+            Entry::from_uncompressed(0x1060, 0x10C0, 0x1060),
+            Entry::from(0x2000, 0x3000, 0x10C0, 0x1400),
+            Entry::from(0x3000, 0x3010, 0x1400, 0x1420),
+            Entry::from(0x4000, 0x4100, 0xFFFFFFFF, 0xFFFFFFFF),
+            Entry::from_uncompressed(0x5000, 0x4000, 0x1420),
+            Entry::default(),
+        ]);
+        let report = table.report();
+        assert_eq!(report, TableReport {
+            compressed: 2,
+            decompressed: 3,
+            does_not_exist: 1,
+            empty: 1,
+            invalid: 1,
+            physical_bytes: 0x1060 + 0x60 + 0x340 + 0x20,
+            virtual_bytes: 0x1060 + 0x60 + 0x1000 + 0x10,
+            unhelpful: 1,
+        });
+    }
 }
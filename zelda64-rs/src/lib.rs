@@ -1,10 +1,16 @@
 #![warn(rust_2018_idioms)]
 
+pub mod codec;
 pub mod common;
+pub mod compress;
 pub mod decompress;
 pub mod dma;
+#[cfg(feature = "serde")]
+pub mod manifest;
 pub mod models;
 pub mod primitive;
 pub mod rom;
 pub mod segment;
+#[cfg(test)]
+mod testing;
 mod util;
@@ -0,0 +1,82 @@
+//! Machine-readable listing of files extracted from a rom.
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::dma::{EntryType, Table};
+use crate::rom::file_name;
+
+/// Manifest entry describing a table entry and its extracted file.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ManifestEntry {
+    /// Index of the entry in the table.
+    pub index: usize,
+    /// Virtual address range.
+    pub virt: Range<u32>,
+    /// Physical address range of the file data in the rom, if any.
+    pub phys: Option<Range<u32>>,
+    /// Entry type.
+    pub kind: EntryType,
+    /// Name of the extracted file, if the entry has file data.
+    pub file: Option<String>,
+}
+
+/// Manifest of all table entries.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Create `Manifest` for the entries of a `Table`, with files named by `file_name`.
+    pub fn from_table(table: &Table) -> Self {
+        let entries = table.entries.iter().enumerate().map(|(index, entry)| {
+            let (phys, kind) = entry.range();
+            let file = phys.as_ref().map(|_| file_name(index));
+            ManifestEntry {
+                index,
+                virt: entry.virt(),
+                phys,
+                kind,
+                file,
+            }
+        }).collect();
+        Self { entries }
+    }
+
+    /// Parse `Manifest` from JSON.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Serialize `Manifest` to pretty-printed JSON.
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dma::Entry;
+
+    #[test]
+    fn manifest_json() {
+        let mut table = Table::new(0x1060, 4).unwrap();
+        table.entries[2] = Entry::from(0x10A0, 0x10B0, 0x1100, 0x1108);
+        table.entries[3] = Entry::from(0x10B0, 0x10C0, 0xFFFF_FFFF, 0xFFFF_FFFF);
+        let manifest = Manifest::from_table(&table);
+        assert_eq!(manifest.entries[2], ManifestEntry {
+            index: 2,
+            virt: 0x10A0..0x10B0,
+            phys: Some(0x1100..0x1108),
+            kind: EntryType::Compressed,
+            file: Some(String::from("0002")),
+        });
+        assert_eq!(manifest.entries[3].file, None);
+
+        let json = manifest.to_json().unwrap();
+        assert!(json.contains("\"kind\": \"Compressed\""));
+        assert_eq!(Manifest::from_json(&json).unwrap(), manifest);
+    }
+}
@@ -0,0 +1,112 @@
+use byteorder::{BigEndian, ReadBytesExt};
+use std::fmt;
+use std::io::{self, Cursor, Seek, SeekFrom};
+
+use crate::common::FromBytes;
+use crate::primitive::Vec3s;
+use crate::segment::SegAddr;
+
+/// Scene or room collision header structure.
+pub struct CollisionHeader {
+    /// Minimum corner of the bounding box.
+    pub min_bounds: Vec3s,
+    /// Maximum corner of the bounding box.
+    pub max_bounds: Vec3s,
+    /// Count of vertices.
+    pub vertex_count: u16,
+    /// Segmented address to vertex list.
+    pub vertices: SegAddr,
+    /// Count of polygons.
+    pub polygon_count: u16,
+    /// Segmented address to polygon list.
+    pub polygons: SegAddr,
+    /// Segmented address to surface type list.
+    pub surface_types: SegAddr,
+    /// Segmented address to camera data list.
+    pub cameras: SegAddr,
+    /// Count of water boxes.
+    pub water_box_count: u16,
+    /// Segmented address to water box list.
+    pub water_boxes: SegAddr,
+}
+
+impl CollisionHeader {
+    /// Size of `CollisionHeader` when serialized.
+    pub const SIZE: usize = 0x2C;
+}
+
+impl FromBytes for CollisionHeader {
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let min_bounds = Vec3s::from_bytes(bytes)?;
+        let max_bounds = Vec3s::from_bytes(bytes.get(Vec3s::SIZE..).unwrap_or_default())?;
+        let mut cursor = Cursor::new(bytes);
+        cursor.seek(SeekFrom::Start(0xC))?;
+        // Counts are 16-bit, padded to a full word.
+        let vertex_count = cursor.read_u16::<BigEndian>()?;
+        cursor.seek(SeekFrom::Current(2))?;
+        let vertices = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
+        let polygon_count = cursor.read_u16::<BigEndian>()?;
+        cursor.seek(SeekFrom::Current(2))?;
+        let polygons = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
+        let surface_types = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
+        let cameras = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
+        let water_box_count = cursor.read_u16::<BigEndian>()?;
+        cursor.seek(SeekFrom::Current(2))?;
+        let water_boxes = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
+        let header = Self {
+            min_bounds,
+            max_bounds,
+            vertex_count,
+            vertices,
+            polygon_count,
+            polygons,
+            surface_types,
+            cameras,
+            water_box_count,
+            water_boxes,
+        };
+        Ok(header)
+    }
+}
+
+impl fmt::Display for CollisionHeader {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(formatter, "Min Bounds:    {}", self.min_bounds)?;
+        writeln!(formatter, "Max Bounds:    {}", self.max_bounds)?;
+        writeln!(formatter, "Vertices:      0x{:04X} @ {}", self.vertex_count, self.vertices)?;
+        writeln!(formatter, "Polygons:      0x{:04X} @ {}", self.polygon_count, self.polygons)?;
+        writeln!(formatter, "Surface Types: {}", self.surface_types)?;
+        writeln!(formatter, "Cameras:       {}", self.cameras)?;
+        writeln!(formatter, "Water Boxes:   0x{:04X} @ {}", self.water_box_count, self.water_boxes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn collision_header() {
+        let bytes = [
+            0xFF, 0x00, 0x00, 0x10, 0xFC, 0x00, // min bounds
+            0x01, 0x00, 0x02, 0x00, 0x03, 0x00, // max bounds
+            0x00, 0x24, 0xAA, 0xAA, 0x02, 0x00, 0x10, 0x00, // vertices
+            0x00, 0x30, 0xAA, 0xAA, 0x02, 0x00, 0x20, 0x00, // polygons
+            0x02, 0x00, 0x30, 0x00, // surface types
+            0x00, 0x00, 0x00, 0x00, // cameras
+            0x00, 0x01, 0xAA, 0xAA, 0x02, 0x00, 0x40, 0x00, // water boxes
+        ];
+        let header = CollisionHeader::from_bytes(&bytes).unwrap();
+        assert_eq!(header.min_bounds, Vec3s::from(-256, 16, -1024));
+        assert_eq!(header.max_bounds, Vec3s::from(256, 512, 768));
+        assert_eq!(header.vertex_count, 0x24);
+        assert_eq!(header.vertices, SegAddr::from(2, 0x1000));
+        assert_eq!(header.polygon_count, 0x30);
+        assert_eq!(header.polygons, SegAddr::from(2, 0x2000));
+        assert_eq!(header.surface_types, SegAddr::from(2, 0x3000));
+        assert_eq!(header.cameras, SegAddr::from_raw(0));
+        assert_eq!(header.water_box_count, 1);
+        assert_eq!(header.water_boxes, SegAddr::from(2, 0x4000));
+        assert!(CollisionHeader::from_bytes(&bytes[..CollisionHeader::SIZE - 1]).is_err());
+    }
+}
@@ -0,0 +1,223 @@
+use byteorder::{BigEndian, ByteOrder};
+use std::io;
+
+use crate::common::FromBytes;
+use crate::models::hierarchy::resolve;
+use crate::primitive::Vec3s;
+use crate::segment::SegAddr;
+
+/// Size of a single vertex (`Vtx`) in vertex data.
+pub const VTX_SIZE: usize = 0x10;
+
+/// Number of vertices in the F3DEX2 vertex buffer.
+const VERTEX_BUFFER_SIZE: usize = 32;
+
+/// Maximum depth of nested display list calls which are followed, so that malformed data cannot recurse forever.
+const MAX_CALL_DEPTH: usize = 16;
+
+/// Size of a single F3DEX2 display list command.
+pub const COMMAND_SIZE: usize = 8;
+
+/// F3DEX2 opcode for loading vertices into the vertex buffer.
+pub const G_VTX: u8 = 0x01;
+/// F3DEX2 opcode for drawing one triangle.
+pub const G_TRI1: u8 = 0x05;
+/// F3DEX2 opcode for drawing two triangles.
+pub const G_TRI2: u8 = 0x06;
+/// F3DEX2 opcode for drawing a quad, as two triangles.
+pub const G_QUAD: u8 = 0x07;
+/// F3DEX2 opcode for calling or branching to another display list.
+pub const G_DL: u8 = 0xDE;
+/// F3DEX2 opcode for ending a display list.
+pub const G_ENDDL: u8 = 0xDF;
+/// F3DEX2 opcode for setting the texture image address.
+pub const G_SETTIMG: u8 = 0xFD;
+
+/// Iterate over the `(w0, w1)` words of F3DEX2 commands in a display list starting at the given offset.
+///
+/// Iteration stops after `G_ENDDL`, after a `G_DL` branch which does not return, or at the end of the data.
+fn commands(bytes: &[u8], offset: usize) -> impl Iterator<Item = (u32, u32)> + '_ {
+    let mut done = false;
+    bytes
+        .get(offset..)
+        .unwrap_or_default()
+        .chunks_exact(COMMAND_SIZE)
+        .map(|command| (BigEndian::read_u32(&command[..4]), BigEndian::read_u32(&command[4..])))
+        .take_while(move |&(w0, _)| {
+            if done {
+                return false;
+            }
+            let opcode = (w0 >> 24) as u8;
+            // Branch without push (parameter 1) never returns to this display list.
+            done = opcode == G_ENDDL || (opcode == G_DL && (w0 >> 16) & 0xFF == 1);
+            true
+        })
+}
+
+/// Collect segmented addresses of textures set with `G_SETTIMG` in an F3DEX2 display list.
+///
+/// All other commands are skipped, including calls into other display lists, which are not followed.
+pub fn texture_refs(bytes: &[u8], offset: usize) -> Vec<SegAddr> {
+    commands(bytes, offset)
+        .filter(|&(w0, _)| (w0 >> 24) as u8 == G_SETTIMG)
+        .map(|(_, w1)| SegAddr::from_raw(w1))
+        .collect()
+}
+
+/// Static geometry drawn by a display list.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Mesh {
+    /// Vertex positions, in the order loaded.
+    pub vertices: Vec<Vec3s>,
+    /// Triangles as indexes into `vertices`.
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// Decode the three vertex buffer indexes of a triangle, stored doubled in the low 24 bits of a command word.
+fn triangle_slots(word: u32) -> [usize; 3] {
+    [(word >> 17) as usize & 0x7F, (word >> 9) as usize & 0x7F, (word >> 1) as usize & 0x7F]
+}
+
+/// Walk an F3DEX2 display list, collecting loaded vertices and drawn triangles into the mesh.
+fn walk(
+    bytes: &[u8],
+    offset: usize,
+    base: SegAddr,
+    depth: usize,
+    mesh: &mut Mesh,
+    buffer: &mut [Option<usize>; VERTEX_BUFFER_SIZE],
+) -> io::Result<()> {
+    for (w0, w1) in commands(bytes, offset) {
+        match (w0 >> 24) as u8 {
+            G_VTX => {
+                let count = (w0 >> 12) as usize & 0xFF;
+                let end = (w0 >> 1) as usize & 0x7F;
+                let start = end.checked_sub(count).filter(|_| end <= VERTEX_BUFFER_SIZE).ok_or_else(|| {
+                    let message = format!("Invalid vertex load of {} vertices ending at {}", count, end);
+                    io::Error::new(io::ErrorKind::InvalidData, message)
+                })?;
+                let data = resolve(bytes, SegAddr::from_raw(w1), base)?;
+                for (slot, idx) in (start..end).zip(0..) {
+                    let vertex = data.get(idx * VTX_SIZE..).ok_or(io::ErrorKind::UnexpectedEof)?;
+                    buffer[slot] = Some(mesh.vertices.len());
+                    mesh.vertices.push(Vec3s::from_bytes(vertex)?);
+                }
+            }
+            opcode @ (G_TRI1 | G_TRI2 | G_QUAD) => {
+                let words: &[u32] = if opcode == G_TRI1 { &[w0] } else { &[w0, w1] };
+                for &word in words {
+                    let slots = triangle_slots(word);
+                    // Triangles using vertices which were never loaded are skipped.
+                    let vertices = slots.iter().map(|&slot| buffer.get(slot).copied().flatten());
+                    if let Some(vertices) = vertices.collect::<Option<Vec<usize>>>() {
+                        mesh.triangles.push([vertices[0], vertices[1], vertices[2]]);
+                    }
+                }
+            }
+            G_DL if depth < MAX_CALL_DEPTH => {
+                let address = SegAddr::from_raw(w1);
+                let target = address.offset().checked_sub(base.offset()).filter(|_| address.segment() == base.segment());
+                // Display lists in other segments cannot be resolved, and are skipped.
+                if let Some(target) = target {
+                    walk(bytes, target as usize, base, depth + 1, mesh, buffer)?;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Collect static geometry of an F3DEX2 display list at the given segmented address, for object data beginning at the
+/// `base` segmented address.
+///
+/// Calls and branches to display lists in the object data are followed, while those in other segments are skipped.
+/// Vertex data must be in the object data.
+pub fn mesh(bytes: &[u8], address: SegAddr, base: SegAddr) -> io::Result<Mesh> {
+    let data = resolve(bytes, address, base)?;
+    let offset = bytes.len() - data.len();
+    let mut mesh = Mesh::default();
+    walk(bytes, offset, base, 0, &mut mesh, &mut [None; VERTEX_BUFFER_SIZE])?;
+    Ok(mesh)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(w0: u32, w1: u32) -> Vec<u8> {
+        let mut bytes = vec![0; COMMAND_SIZE];
+        BigEndian::write_u32(&mut bytes[..4], w0);
+        BigEndian::write_u32(&mut bytes[4..], w1);
+        bytes
+    }
+
+    #[test]
+    fn texture_refs_settimg() {
+        let bytes: Vec<u8> = [
+            command(0xE700_0000, 0),
+            command(0xFD10_0000, 0x0600_1000),
+            command(0xDE00_0000, 0x0600_2000),
+            command(0xFD10_0000, 0x0800_0000),
+            command(0xDF00_0000, 0),
+            command(0xFD10_0000, 0x0600_3000),
+        ].concat();
+        let refs = texture_refs(&bytes, 0);
+        assert_eq!(refs, vec![SegAddr::from(6, 0x1000), SegAddr::from(8, 0)]);
+        assert_eq!(texture_refs(&bytes, COMMAND_SIZE * 2), vec![SegAddr::from(8, 0)]);
+        assert!(texture_refs(&bytes, bytes.len() + 1).is_empty());
+    }
+
+    #[test]
+    fn texture_refs_branch() {
+        let bytes: Vec<u8> = [
+            command(0xFD10_0000, 0x0600_1000),
+            command(0xDE01_0000, 0x0600_2000),
+            command(0xFD10_0000, 0x0600_3000),
+        ].concat();
+        assert_eq!(texture_refs(&bytes, 0), vec![SegAddr::from(6, 0x1000)]);
+    }
+
+    /// Vertex data with the given positions.
+    fn vertices(positions: &[(i16, i16, i16)]) -> Vec<u8> {
+        let mut bytes = vec![0; positions.len() * VTX_SIZE];
+        for (chunk, &(x, y, z)) in bytes.chunks_exact_mut(VTX_SIZE).zip(positions) {
+            BigEndian::write_i16_into(&[x, y, z], &mut chunk[..6]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn mesh_triangles() {
+        // Vertices at 0x00, display list at 0x40 calling another at 0x60.
+        let mut bytes = vertices(&[(0, 0, 0), (1, 0, 0), (0, 1, 0), (1, 1, 0)]);
+        bytes.extend([
+            command(0x0100_4008, 0x0600_0000),
+            command(0x0500_0204, 0),
+            command(0xDE00_0000, 0x0600_0060),
+            command(0xDF00_0000, 0),
+        ].concat());
+        bytes.extend([
+            command(0x0600_0204, 0x0002_0406),
+            command(0x0500_0A0C, 0),
+            command(0xDE00_0000, 0x0800_0000),
+            command(0xDF00_0000, 0),
+        ].concat());
+        let mesh = mesh(&bytes, SegAddr::from(6, 0x40), SegAddr::from(6, 0)).unwrap();
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.vertices[3], Vec3s::from(1, 1, 0));
+        // Triangle using unloaded vertices is skipped.
+        assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 1, 2], [1, 2, 3]]);
+    }
+
+    #[test]
+    fn mesh_errors() {
+        let bytes = [command(0x0100_4008, 0x0600_0100), command(0xDF00_0000, 0)].concat();
+        assert!(mesh(&bytes, SegAddr::from(6, 0), SegAddr::from(6, 0)).is_err());
+        let bytes = [command(0x0100_4002, 0x0600_0000), command(0xDF00_0000, 0)].concat();
+        assert!(mesh(&bytes, SegAddr::from(6, 0), SegAddr::from(6, 0)).is_err());
+        // Recursive calls stop at the maximum depth.
+        let bytes = [command(0xDE00_0000, 0x0600_0000), command(0xDF00_0000, 0)].concat();
+        assert_eq!(mesh(&bytes, SegAddr::from(6, 0), SegAddr::from(6, 0)).unwrap(), Mesh::default());
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use byteorder::{BigEndian, ReadBytesExt};
+use std::collections::HashSet;
 use std::fmt::{self, Write};
 use std::io::{self, Cursor};
 
@@ -10,6 +11,32 @@ use crate::segment::{Relative, SegAddr};
 pub type Hierarchy = HierarchyWith<Limb>;
 pub type PlayerHierarchy = HierarchyWith<PlayerLimb>;
 
+/// Limb index value indicating there is no child or next limb.
+pub const LIMB_NONE: u8 = 0xFF;
+
+/// Limb types which reference display lists.
+pub trait DisplayLists {
+    /// Get addresses of all display lists referenced by the limb, including null addresses.
+    fn display_lists(&self) -> Vec<SegAddr>;
+}
+
+/// Get object data beginning at offset, or an error if the offset is past the end.
+pub(crate) fn bytes_at(bytes: &[u8], offset: u32) -> io::Result<&[u8]> {
+    bytes.get(offset as usize..).ok_or_else(|| io::ErrorKind::UnexpectedEof.into())
+}
+
+/// Get object data at a segmented address, for object data beginning at the `base` segmented address.
+pub(crate) fn resolve(bytes: &[u8], address: SegAddr, base: SegAddr) -> io::Result<&[u8]> {
+    let offset = match address.offset().checked_sub(base.offset()) {
+        Some(offset) if address.segment() == base.segment() => offset,
+        _ => {
+            let message = format!("Address {} is outside of object data at {}", address, base);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+        }
+    };
+    bytes_at(bytes, offset)
+}
+
 /// Hierarchy header structure.
 pub struct Header {
     /// Segmented address to beginning of limb index.
@@ -42,9 +69,9 @@ impl FromBytes for Header {
 
 impl fmt::Display for Header {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(formatter, "Limb Indexes:  {}\n", self.limbs)?;
-        write!(formatter, "Limb Count:    0x{:02X}\n", self.count)?;
-        write!(formatter, "Display Lists: 0x{:02X}\n", self.display_lists)
+        writeln!(formatter, "Limb Indexes:  {}", self.limbs)?;
+        writeln!(formatter, "Limb Count:    0x{:02X}", self.count)?;
+        writeln!(formatter, "Display Lists: 0x{:02X}", self.display_lists)
     }
 }
 
@@ -65,6 +92,18 @@ impl Limb {
     pub const SIZE: usize = 0xC;
 }
 
+impl AsRef<Limb> for Limb {
+    fn as_ref(&self) -> &Limb {
+        self
+    }
+}
+
+impl DisplayLists for Limb {
+    fn display_lists(&self) -> Vec<SegAddr> {
+        vec![self.display_list]
+    }
+}
+
 impl FromBytes for Limb {
     fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
         let translation = Vec3s::from_bytes(bytes)?;
@@ -84,10 +123,10 @@ impl FromBytes for Limb {
 
 impl fmt::Display for Limb {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(formatter, "Translation: {}\n", self.translation)?;
-        write!(formatter, "Child Limb:  {:#02X}\n", self.child)?;
-        write!(formatter, "Next Limb:   {:#02X}\n", self.next)?;
-        write!(formatter, "DisplayList: {}\n", self.display_list)
+        writeln!(formatter, "Translation: {}", self.translation)?;
+        writeln!(formatter, "Child Limb:  {:#X}", self.child)?;
+        writeln!(formatter, "Next Limb:   {:#X}", self.next)?;
+        writeln!(formatter, "DisplayList: {}", self.display_list)
     }
 }
 
@@ -113,10 +152,62 @@ impl FromBytes for PlayerLimb {
     }
 }
 
+impl DisplayLists for PlayerLimb {
+    fn display_lists(&self) -> Vec<SegAddr> {
+        vec![self.base.display_list, self.far_model_display_list]
+    }
+}
+
+impl AsRef<Limb> for PlayerLimb {
+    fn as_ref(&self) -> &Limb {
+        &self.base
+    }
+}
+
 impl fmt::Display for PlayerLimb {
     fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(formatter, "{}", self.base)?;
-        write!(formatter, "Far Model:   {}\n", self.far_model_display_list)
+        writeln!(formatter, "Far Model:   {}", self.far_model_display_list)
+    }
+}
+
+/// Limb node with resolved children.
+pub struct Node {
+    /// Index of the limb in the hierarchy limb list.
+    pub index: usize,
+    /// Translation relative to the parent node.
+    pub translation: Vec3s,
+    /// Address to display list.
+    pub display_list: SegAddr,
+    /// Child nodes, in sibling order.
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    /// Write this node and its children, indenting each level of depth by two spaces.
+    fn write_indented<W: io::Write>(&self, writer: &mut W, depth: usize) -> io::Result<()> {
+        writeln!(writer, "{:indent$}Limb [{}]: Translation: {}, DisplayList: {}", "", self.index,
+            self.translation, self.display_list, indent = depth * 2)?;
+        for child in &self.children {
+            child.write_indented(writer, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Tree of limb nodes, resolved from the first-child/next-sibling encoding of a hierarchy.
+pub struct NodeTree {
+    /// Root nodes. A well-formed hierarchy has a single root, limb 0.
+    pub roots: Vec<Node>,
+}
+
+impl NodeTree {
+    /// Write each node on its own line, with children indented under their parent.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        for root in &self.roots {
+            root.write_indented(writer, 0)?;
+        }
+        Ok(())
     }
 }
 
@@ -135,16 +226,15 @@ impl<T: fmt::Display + FromBytes> HierarchyWith<T> {
 
     /// Read from object data with `Header` at specified offset.
     ///
-    /// TODO: Ensure base segment index matches `header.limbs.segment()`?
-    pub fn read_from(bytes: &[u8], offset: u32, _base: SegAddr) -> io::Result<Self> {
-        let header = Header::from_bytes(&bytes[(offset as usize)..])?;
-        let indexes_offset = header.limbs.offset() as usize;
-        let mut cursor = Cursor::new(&bytes[indexes_offset..]);
+    /// The object data begins at the `base` segmented address. Limb index and limb addresses must be in the same
+    /// segment as `base`, and are translated to offsets relative to it.
+    pub fn read_from(bytes: &[u8], offset: u32, base: SegAddr) -> io::Result<Self> {
+        let header = Header::from_bytes(bytes_at(bytes, offset)?)?;
+        let mut cursor = Cursor::new(resolve(bytes, header.limbs, base)?);
         let mut limbs = Vec::with_capacity(header.count as usize);
         for _ in 0..header.count {
             let index = SegAddr::from_raw(cursor.read_u32::<BigEndian>()?);
-            let limb_offset = index.offset() as usize;
-            let limb = T::from_bytes(&bytes[limb_offset..])?;
+            let limb = T::from_bytes(resolve(bytes, index, base)?)?;
             let relative = Relative::from(index, limb);
             limbs.push(relative);
         }
@@ -152,13 +242,13 @@ impl<T: fmt::Display + FromBytes> HierarchyWith<T> {
     }
 
     pub fn to_paragraph<W: io::Write>(&self, writer: &mut W) -> Result<()> {
-        write!(writer, "Header:\n")?;
-        write!(writer, "{}\n", self.header)?;
+        writeln!(writer, "Header:")?;
+        writeln!(writer, "{}", self.header)?;
 
         // Print each limb info, padded.
         let mut buffer = String::new();
         for (idx, limb) in self.limbs.iter().enumerate() {
-            write!(writer, "Limb [{0}]: {1}\n", idx, limb.address)?;
+            writeln!(writer, "Limb [{0}]: {1}", idx, limb.address)?;
             // Write limb info to temp buffer for padding.
             write!(&mut buffer, "{}", limb.value)?;
             for line in buffer.lines() {
@@ -169,3 +259,189 @@ impl<T: fmt::Display + FromBytes> HierarchyWith<T> {
         Ok(())
     }
 }
+
+impl<T: fmt::Display + FromBytes + AsRef<Limb>> HierarchyWith<T> {
+    /// Get the limb at the given index, if any.
+    fn limb(&self, index: u8) -> Option<&Limb> {
+        self.limbs.get(index as usize).map(|limb| limb.value.as_ref())
+    }
+
+    /// Build nodes for the sibling chain beginning at the given limb index.
+    ///
+    /// Limbs which are out of range or were already visited end the chain, so malformed data cannot recurse forever.
+    fn sibling_nodes(&self, first: u8, visited: &mut [bool]) -> Vec<Node> {
+        let mut nodes = Vec::new();
+        let mut current = first;
+        while current != LIMB_NONE {
+            let index = current as usize;
+            let limb = match self.limb(current) {
+                Some(limb) if !visited[index] => limb,
+                _ => break,
+            };
+            visited[index] = true;
+            let children = self.sibling_nodes(limb.child, visited);
+            nodes.push(Node {
+                index,
+                translation: limb.translation,
+                display_list: limb.display_list,
+                children,
+            });
+            current = limb.next;
+        }
+        nodes
+    }
+
+    /// Resolve limbs into a parent-child tree, beginning at limb 0.
+    pub fn to_node_tree(&self) -> NodeTree {
+        let mut visited = vec![false; self.limbs.len()];
+        let roots = self.sibling_nodes(0, &mut visited);
+        NodeTree { roots }
+    }
+
+    /// Write limbs as a tree view, following child and next links with children indented under parents.
+    ///
+    /// Limbs which would form a cycle are skipped, see `to_node_tree`.
+    pub fn to_tree<W: io::Write>(&self, writer: &mut W) -> Result<()> {
+        self.to_node_tree().write(writer)?;
+        Ok(())
+    }
+}
+
+impl<T: fmt::Display + FromBytes + DisplayLists> HierarchyWith<T> {
+    /// Get indexes of all segments referenced by the hierarchy, which must be loaded to render it.
+    ///
+    /// Includes the segments of the limb index, each limb and each non-null display list.
+    pub fn referenced_segments(&self) -> HashSet<u8> {
+        let limbs = self.limbs.iter().map(|limb| limb.address);
+        let display_lists = self.limbs.iter()
+            .flat_map(|limb| limb.value.display_lists())
+            .filter(|address| address.raw() != 0);
+        std::iter::once(self.header.limbs)
+            .chain(limbs)
+            .chain(display_lists)
+            .map(|address| address.segment())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::WriteBytesExt;
+
+    /// Limb described by (translation, child, next, display list).
+    type LimbSpec = ((i16, i16, i16), u8, u8, u32);
+
+    /// Build object data for a hierarchy with the given limbs.
+    fn hierarchy_bytes(limbs: &[LimbSpec]) -> Vec<u8> {
+        let count = limbs.len();
+        let mut bytes = Vec::new();
+        // Header, followed by limb indexes, followed by limbs.
+        bytes.write_u32::<BigEndian>(0x0600_000C).unwrap();
+        bytes.write_u32::<BigEndian>((count as u32) << 24).unwrap();
+        bytes.write_u32::<BigEndian>((count as u32) << 24).unwrap();
+        let limbs_offset = 0xC + (count * 4);
+        for idx in 0..count {
+            let offset = limbs_offset + (idx * Limb::SIZE);
+            bytes.write_u32::<BigEndian>(0x0600_0000 | offset as u32).unwrap();
+        }
+        for &((x, y, z), child, next, display_list) in limbs {
+            bytes.write_i16::<BigEndian>(x).unwrap();
+            bytes.write_i16::<BigEndian>(y).unwrap();
+            bytes.write_i16::<BigEndian>(z).unwrap();
+            bytes.write_u8(child).unwrap();
+            bytes.write_u8(next).unwrap();
+            bytes.write_u32::<BigEndian>(display_list).unwrap();
+        }
+        bytes
+    }
+
+    #[test]
+    fn node_tree() {
+        // 0 -> [1 -> [3], 2]
+        let bytes = hierarchy_bytes(&[
+            ((0, 10, 0), 1, LIMB_NONE, 0),
+            ((1, 0, 0), 3, 2, 0x0600_1000),
+            ((2, 0, 0), LIMB_NONE, LIMB_NONE, 0x0600_2000),
+            ((3, 0, 0), LIMB_NONE, LIMB_NONE, 0),
+        ]);
+        let hierarchy = Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0)).unwrap();
+        let tree = hierarchy.to_node_tree();
+
+        assert_eq!(tree.roots.len(), 1);
+        let root = &tree.roots[0];
+        assert_eq!(root.translation, Vec3s::from(0, 10, 0));
+        let children: Vec<usize> = root.children.iter().map(|node| node.index).collect();
+        assert_eq!(children, vec![1, 2]);
+        assert_eq!(root.children[0].children[0].index, 3);
+        assert_eq!(root.children[1].display_list, SegAddr::from_raw(0x0600_2000));
+    }
+
+    #[test]
+    fn to_tree() {
+        let bytes = hierarchy_bytes(&[
+            ((0, 10, 0), 1, LIMB_NONE, 0),
+            ((1, 0, 0), 2, LIMB_NONE, 0x0600_1000),
+            ((2, 0, 0), LIMB_NONE, 0, 0),
+        ]);
+        let hierarchy = Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0)).unwrap();
+        let mut output = Vec::new();
+        hierarchy.to_tree(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        // Limb 2 lists limb 0 as its next sibling, which is dropped as a cycle.
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("Limb [0]: Translation: (0, 10, 0)"));
+        assert!(lines[1].starts_with("  Limb [1]: "));
+        assert!(lines[1].ends_with(&format!("DisplayList: {}", SegAddr::from_raw(0x0600_1000))));
+        assert!(lines[2].starts_with("    Limb [2]: "));
+    }
+
+    #[test]
+    fn node_tree_cycle() {
+        // Limb 1 lists limb 0 as its child.
+        let bytes = hierarchy_bytes(&[
+            ((0, 0, 0), 1, LIMB_NONE, 0),
+            ((0, 0, 0), 0, LIMB_NONE, 0),
+        ]);
+        let hierarchy = Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0)).unwrap();
+        let tree = hierarchy.to_node_tree();
+        assert_eq!(tree.roots[0].children.len(), 1);
+        assert!(tree.roots[0].children[0].children.is_empty());
+    }
+
+    #[test]
+    fn read_from_base() {
+        let bytes = hierarchy_bytes(&[((0, 0, 0), LIMB_NONE, LIMB_NONE, 0)]);
+        assert!(Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0)).is_ok());
+        let err = Hierarchy::read_from(&bytes, 0, SegAddr::from(4, 0)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0x10)).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        // Data may begin partway into the segment, with all addresses shifted accordingly.
+        let mut shifted = bytes.clone();
+        shifted[..4].copy_from_slice(&[0x06, 0x00, 0x00, 0x10]);
+        shifted[0xC..0x10].copy_from_slice(&[0x06, 0x00, 0x00, 0x14]);
+        let hierarchy = Hierarchy::read_from(&shifted, 0, SegAddr::from(6, 4)).unwrap();
+        assert_eq!(hierarchy.limbs[0].address, SegAddr::from(6, 0x14));
+    }
+
+    #[test]
+    fn referenced_segments() {
+        let bytes = hierarchy_bytes(&[
+            ((0, 0, 0), 1, LIMB_NONE, 0),
+            ((0, 0, 0), LIMB_NONE, LIMB_NONE, 0x0400_1000),
+        ]);
+        let hierarchy = Hierarchy::read_from(&bytes, 0, SegAddr::from(6, 0)).unwrap();
+        let expected: HashSet<u8> = [4, 6].iter().cloned().collect();
+        assert_eq!(hierarchy.referenced_segments(), expected);
+
+        let HierarchyWith { header, mut limbs } = hierarchy;
+        let limb = limbs.pop().unwrap();
+        let player = PlayerLimb { base: limb.value, far_model_display_list: SegAddr::from(5, 0x2000) };
+        let player_hierarchy = PlayerHierarchy::from(header, vec![Relative::from(limb.address, player)]);
+        let expected: HashSet<u8> = [4, 5, 6].iter().cloned().collect();
+        assert_eq!(player_hierarchy.referenced_segments(), expected);
+    }
+}
@@ -1 +1,8 @@
+pub mod collision;
+pub mod displaylist;
 pub mod hierarchy;
+pub mod obj;
+pub mod object;
+pub mod scene;
+
+pub use self::obj::export_obj;
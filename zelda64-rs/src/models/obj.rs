@@ -0,0 +1,84 @@
+use std::io::{self, Write};
+
+use crate::models::displaylist;
+use crate::models::hierarchy::{Hierarchy, Node};
+use crate::models::object::OBJECT_SEGMENT;
+use crate::primitive::Vec3s;
+use crate::segment::SegAddr;
+
+/// Write the mesh of each node in a tree, offset by the accumulated translations of its parents.
+fn write_nodes<W: Write>(
+    bytes: &[u8],
+    nodes: &[Node],
+    parent: (i32, i32, i32),
+    base: SegAddr,
+    writer: &mut W,
+    vertex_count: &mut usize,
+) -> io::Result<()> {
+    for node in nodes {
+        let Vec3s { x, y, z } = node.translation;
+        let origin = (parent.0 + x as i32, parent.1 + y as i32, parent.2 + z as i32);
+        if node.display_list.raw() != 0 {
+            let mesh = displaylist::mesh(bytes, node.display_list, base)?;
+            writeln!(writer, "o limb_{}", node.index)?;
+            for vertex in &mesh.vertices {
+                let (x, y, z) = (origin.0 + vertex.x as i32, origin.1 + vertex.y as i32, origin.2 + vertex.z as i32);
+                writeln!(writer, "v {} {} {}", x, y, z)?;
+            }
+            // Face indexes are 1-based and count all vertices written so far.
+            for triangle in &mesh.triangles {
+                let [a, b, c] = triangle.map(|index| *vertex_count + index + 1);
+                writeln!(writer, "f {} {} {}", a, b, c)?;
+            }
+            *vertex_count += mesh.vertices.len();
+        }
+        write_nodes(bytes, &node.children, origin, base, writer, vertex_count)?;
+    }
+    Ok(())
+}
+
+/// Export the static geometry of the `Hierarchy` at the given offset in object data as a Wavefront OBJ.
+///
+/// Each limb with a display list is written as an object named by its limb index (such as `limb_0`), with vertices
+/// offset by the translations of the limb and its parents. Only positions and triangles are exported, so limb
+/// rotations, textures and matrices are ignored. Object data is assumed to be loaded into `OBJECT_SEGMENT`.
+pub fn export_obj<W: Write>(object_bytes: &[u8], hierarchy_offset: u32, writer: &mut W) -> io::Result<()> {
+    let base = SegAddr::from(OBJECT_SEGMENT, 0);
+    let hierarchy = Hierarchy::read_from(object_bytes, hierarchy_offset, base)?;
+    let tree = hierarchy.to_node_tree();
+    let mut vertex_count = 0;
+    write_nodes(object_bytes, &tree.roots, (0, 0, 0), base, writer, &mut vertex_count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use byteorder::{BigEndian, ByteOrder};
+
+    use crate::models::hierarchy::LIMB_NONE;
+
+    fn words(values: &[u32]) -> Vec<u8> {
+        let mut bytes = vec![0; values.len() * 4];
+        BigEndian::write_u32_into(values, &mut bytes);
+        bytes
+    }
+
+    #[test]
+    fn export_obj_limbs() {
+        // Vertices (0, 0, 0), (10, 0, 0) and (0, 10, 0) at 0x00, followed by a display list drawing them at 0x30.
+        let mut bytes = words(&[0, 0, 0, 0, 0x000A_0000, 0, 0, 0, 0x0000_000A, 0, 0, 0]);
+        bytes.extend(words(&[0x0100_3006, 0x0600_0000, 0x0500_0204, 0, 0xDF00_0000, 0]));
+        // Hierarchy header at 0x48, limb index at 0x54 and limbs at 0x5C.
+        bytes.extend(words(&[0x0600_0054, 0x0200_0000, 0x0200_0000, 0x0600_005C, 0x0600_0068]));
+        // Root limb at (0, 100, 0) without display list, with one child at (5, 0, 0) drawing the triangle.
+        bytes.extend(words(&[0x0000_0064, 0x0000_0100 | LIMB_NONE as u32, 0]));
+        bytes.extend(words(&[0x0005_0000, 0x0000_FFFF, 0x0600_0030]));
+
+        let mut output = Vec::new();
+        export_obj(&bytes, 0x48, &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines, vec!["o limb_1", "v 5 100 0", "v 15 100 0", "v 5 110 0", "f 1 2 3"]);
+        assert!(export_obj(&bytes, 0x100, &mut Vec::new()).is_err());
+    }
+}
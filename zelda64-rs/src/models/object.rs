@@ -0,0 +1,72 @@
+use std::io;
+
+use crate::common::FromBytes;
+use crate::models::hierarchy::{Hierarchy, PlayerHierarchy};
+use crate::segment::SegAddr;
+
+/// Segment index which object files are loaded into.
+pub const OBJECT_SEGMENT: u8 = 6;
+
+/// Decompressed object file data, for parsing model structures at offsets within it.
+pub struct ObjectFile {
+    data: Vec<u8>,
+}
+
+impl ObjectFile {
+    pub fn from(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    /// Get object file data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Read a `Hierarchy` with its header at the given offset.
+    pub fn hierarchy(&self, offset: u32) -> io::Result<Hierarchy> {
+        Hierarchy::read_from(&self.data, offset, self.base())
+    }
+
+    /// Read a `PlayerHierarchy` with its header at the given offset.
+    pub fn player_hierarchy(&self, offset: u32) -> io::Result<PlayerHierarchy> {
+        PlayerHierarchy::read_from(&self.data, offset, self.base())
+    }
+
+    /// Read any `FromBytes` structure at the given offset.
+    pub fn read<T: FromBytes>(&self, offset: u32) -> io::Result<T> {
+        let bytes = self.data.get(offset as usize..).ok_or(io::ErrorKind::UnexpectedEof)?;
+        T::from_bytes(bytes)
+    }
+
+    /// Segmented address of the start of the object file.
+    fn base(&self) -> SegAddr {
+        SegAddr::from(OBJECT_SEGMENT, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::hierarchy::Header;
+
+    #[test]
+    fn object_hierarchy() {
+        // Header at 0x10, pointing to a single limb index at 0x1C and limb at 0x20.
+        let mut data = vec![0; 0x10];
+        data.extend([0x06, 0x00, 0x00, 0x1C, 0x01, 0, 0, 0, 0x01, 0, 0, 0]);
+        data.extend([0x06, 0x00, 0x00, 0x20]);
+        data.extend([0, 1, 0, 2, 0, 3, 0xFF, 0xFF, 0x06, 0x00, 0x10, 0x00]);
+        let object = ObjectFile::from(data.clone());
+
+        let hierarchy = object.hierarchy(0x10).unwrap();
+        assert_eq!(hierarchy.limbs.len(), 1);
+        assert_eq!(hierarchy.limbs[0].address, SegAddr::from(6, 0x20));
+        assert_eq!(hierarchy.limbs[0].value.display_list, SegAddr::from(6, 0x1000));
+        let header: Header = object.read(0x10).unwrap();
+        assert_eq!(header.count, 1);
+        assert!(object.hierarchy(0x100).is_err());
+        // Limb index points past the end of the data.
+        data[0x1C..0x20].copy_from_slice(&[0x06, 0x00, 0x01, 0x00]);
+        assert!(ObjectFile::from(data).hierarchy(0x10).is_err());
+    }
+}
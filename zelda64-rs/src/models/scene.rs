@@ -0,0 +1,203 @@
+use byteorder::{BigEndian, ByteOrder};
+use std::fmt;
+use std::io;
+
+use crate::common::FromBytes;
+use crate::segment::SegAddr;
+
+/// Size of a single scene or room header command.
+pub const COMMAND_SIZE: usize = 8;
+
+/// Opcode of the command ending a scene or room header.
+pub const SCENE_CMD_END: u8 = 0x14;
+
+/// Scene or room header command, with 1-byte opcode, 1-byte parameter and 4-byte operand.
+///
+/// Commands with a segmented address operand are decoded, while all others keep their raw values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SceneCommand {
+    /// `0x00`: Player spawn actor list.
+    SpawnList { count: u8, address: SegAddr },
+    /// `0x01`: Room actor list.
+    ActorList { count: u8, address: SegAddr },
+    /// `0x03`: Scene `CollisionHeader`.
+    CollisionHeader(SegAddr),
+    /// `0x04`: Room list.
+    RoomList { count: u8, address: SegAddr },
+    /// `0x06`: Entrance list.
+    EntranceList(SegAddr),
+    /// `0x0A`: Room mesh header.
+    Mesh(SegAddr),
+    /// `0x0B`: Room object list.
+    ObjectList { count: u8, address: SegAddr },
+    /// `0x0C`: Room light list.
+    LightList { count: u8, address: SegAddr },
+    /// `0x0D`: Path list.
+    PathList(SegAddr),
+    /// `0x0E`: Transition actor list.
+    TransitionActorList { count: u8, address: SegAddr },
+    /// `0x0F`: Light settings list.
+    LightSettingsList { count: u8, address: SegAddr },
+    /// `0x13`: Exit list.
+    ExitList(SegAddr),
+    /// `0x14`: End of header.
+    End,
+    /// `0x17`: Cutscene data.
+    CutsceneData(SegAddr),
+    /// `0x18`: Alternate header list.
+    AlternateHeaders(SegAddr),
+    /// Any other command, with its raw values.
+    Other { code: u8, param: u8, data: u32 },
+}
+
+impl SceneCommand {
+    /// Get the segmented address operand of the command, if it has one.
+    pub fn address(&self) -> Option<SegAddr> {
+        match *self {
+            Self::SpawnList { address, .. }
+            | Self::ActorList { address, .. }
+            | Self::RoomList { address, .. }
+            | Self::ObjectList { address, .. }
+            | Self::LightList { address, .. }
+            | Self::TransitionActorList { address, .. }
+            | Self::LightSettingsList { address, .. } => Some(address),
+            Self::CollisionHeader(address)
+            | Self::EntranceList(address)
+            | Self::Mesh(address)
+            | Self::PathList(address)
+            | Self::ExitList(address)
+            | Self::CutsceneData(address)
+            | Self::AlternateHeaders(address) => Some(address),
+            Self::End | Self::Other { .. } => None,
+        }
+    }
+}
+
+impl FromBytes for SceneCommand {
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let bytes = bytes.get(..COMMAND_SIZE).ok_or(io::ErrorKind::UnexpectedEof)?;
+        let (code, count) = (bytes[0], bytes[1]);
+        let data = BigEndian::read_u32(&bytes[4..]);
+        let address = SegAddr::from_raw(data);
+        let command = match code {
+            0x00 => Self::SpawnList { count, address },
+            0x01 => Self::ActorList { count, address },
+            0x03 => Self::CollisionHeader(address),
+            0x04 => Self::RoomList { count, address },
+            0x06 => Self::EntranceList(address),
+            0x0A => Self::Mesh(address),
+            0x0B => Self::ObjectList { count, address },
+            0x0C => Self::LightList { count, address },
+            0x0D => Self::PathList(address),
+            0x0E => Self::TransitionActorList { count, address },
+            0x0F => Self::LightSettingsList { count, address },
+            0x13 => Self::ExitList(address),
+            SCENE_CMD_END => Self::End,
+            0x17 => Self::CutsceneData(address),
+            0x18 => Self::AlternateHeaders(address),
+            _ => Self::Other { code, param: count, data },
+        };
+        Ok(command)
+    }
+}
+
+impl fmt::Display for SceneCommand {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SpawnList { count, address } => write!(formatter, "Spawn List:            0x{:02X} @ {}", count, address),
+            Self::ActorList { count, address } => write!(formatter, "Actor List:            0x{:02X} @ {}", count, address),
+            Self::CollisionHeader(address) => write!(formatter, "Collision Header:      {}", address),
+            Self::RoomList { count, address } => write!(formatter, "Room List:             0x{:02X} @ {}", count, address),
+            Self::EntranceList(address) => write!(formatter, "Entrance List:         {}", address),
+            Self::Mesh(address) => write!(formatter, "Mesh:                  {}", address),
+            Self::ObjectList { count, address } => write!(formatter, "Object List:           0x{:02X} @ {}", count, address),
+            Self::LightList { count, address } => write!(formatter, "Light List:            0x{:02X} @ {}", count, address),
+            Self::PathList(address) => write!(formatter, "Path List:             {}", address),
+            Self::TransitionActorList { count, address } => {
+                write!(formatter, "Transition Actor List: 0x{:02X} @ {}", count, address)
+            }
+            Self::LightSettingsList { count, address } => {
+                write!(formatter, "Light Settings List:   0x{:02X} @ {}", count, address)
+            }
+            Self::ExitList(address) => write!(formatter, "Exit List:             {}", address),
+            Self::End => write!(formatter, "End"),
+            Self::CutsceneData(address) => write!(formatter, "Cutscene Data:         {}", address),
+            Self::AlternateHeaders(address) => write!(formatter, "Alternate Headers:     {}", address),
+            Self::Other { code, param, data } => {
+                write!(formatter, "Command 0x{:02X}:          0x{:02X}, 0x{:08X}", code, param, data)
+            }
+        }
+    }
+}
+
+/// Command list of a scene or room header.
+pub struct SceneCommands {
+    /// Commands in order, not including the ending `SceneCommand::End`.
+    pub commands: Vec<SceneCommand>,
+}
+
+impl SceneCommands {
+    /// Parse commands beginning at the given offset, until `SceneCommand::End`.
+    ///
+    /// Fails if the data ends before `SceneCommand::End` is found.
+    pub fn parse(bytes: &[u8], offset: usize) -> io::Result<Self> {
+        let mut commands = Vec::new();
+        let mut chunks = bytes.get(offset..).unwrap_or_default().chunks(COMMAND_SIZE);
+        loop {
+            let chunk = chunks.next().ok_or(io::ErrorKind::UnexpectedEof)?;
+            match SceneCommand::from_bytes(chunk)? {
+                SceneCommand::End => break,
+                command => commands.push(command),
+            }
+        }
+        Ok(Self { commands })
+    }
+}
+
+impl fmt::Display for SceneCommands {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for command in &self.commands {
+            writeln!(formatter, "{}", command)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(code: u8, param: u8, data: u32) -> Vec<u8> {
+        let mut bytes = vec![code, param, 0, 0, 0, 0, 0, 0];
+        BigEndian::write_u32(&mut bytes[4..], data);
+        bytes
+    }
+
+    #[test]
+    fn scene_commands() {
+        let bytes: Vec<u8> = [
+            vec![0xFF; 8],
+            command(0x00, 2, 0x0200_0040),
+            command(0x03, 0, 0x0200_1000),
+            command(0x04, 1, 0x0200_0080),
+            command(0x11, 0, 0x0100_0000),
+            command(SCENE_CMD_END, 0, 0),
+            command(0x0A, 0, 0x0300_0000),
+        ].concat();
+        let scene = SceneCommands::parse(&bytes, 8).unwrap();
+        assert_eq!(scene.commands, vec![
+            SceneCommand::SpawnList { count: 2, address: SegAddr::from(2, 0x40) },
+            SceneCommand::CollisionHeader(SegAddr::from(2, 0x1000)),
+            SceneCommand::RoomList { count: 1, address: SegAddr::from(2, 0x80) },
+            SceneCommand::Other { code: 0x11, param: 0, data: 0x0100_0000 },
+        ]);
+        assert_eq!(scene.commands[1].address(), Some(SegAddr::from(2, 0x1000)));
+        assert_eq!(scene.commands[3].address(), None);
+        assert!(scene.to_string().starts_with("Spawn List:            0x02 @ 02:000040\n"));
+
+        // Missing end command.
+        assert!(SceneCommands::parse(&bytes[..0x28], 8).is_err());
+        assert!(SceneCommands::parse(&bytes[..0x2C], 8).is_err());
+        assert!(SceneCommands::parse(&bytes, 0x100).is_err());
+    }
+}
@@ -4,7 +4,13 @@ use std::io::{self, Cursor};
 
 use crate::common::FromBytes;
 
+/// Read a big-endian `f32` from the beginning of the slice.
+pub fn read_f32_be(bytes: &[u8]) -> io::Result<f32> {
+    Cursor::new(bytes).read_f32::<BigEndian>()
+}
+
 /// Vector type with signed 16-bit coordinate values.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct Vec3s {
     pub x: i16,
     pub y: i16,
@@ -43,3 +49,74 @@ impl fmt::Display for Vec3s {
         write!(f, "({0}, {1}, {2})", self.x, self.y, self.z)
     }
 }
+
+/// N64 fixed-point 4x4 matrix, with values decoded to `f32`.
+///
+/// Serialized as 16 signed 16-bit integer parts followed by 16 unsigned 16-bit fraction parts, both in row-major
+/// order, with each value being the 32-bit fixed-point number `(integer << 16) | fraction`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mtx {
+    /// Matrix values in row-major order.
+    pub m: [[f32; 4]; 4],
+}
+
+impl Mtx {
+    /// Size of `Mtx` when serialized.
+    pub const SIZE: usize = 0x40;
+}
+
+impl FromBytes for Mtx {
+    fn from_bytes(bytes: &[u8]) -> io::Result<Self> {
+        let mut cursor = Cursor::new(bytes);
+        let mut integers = [0; 16];
+        let mut fractions = [0; 16];
+        cursor.read_i16_into::<BigEndian>(&mut integers)?;
+        cursor.read_u16_into::<BigEndian>(&mut fractions)?;
+        let mut m = [[0.0; 4]; 4];
+        for (idx, (&integer, &fraction)) in integers.iter().zip(&fractions).enumerate() {
+            let fixed = ((integer as i32) << 16) | fraction as i32;
+            m[idx / 4][idx % 4] = fixed as f32 / 65536.0;
+        }
+        Ok(Self { m })
+    }
+}
+
+impl fmt::Display for Mtx {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in &self.m {
+            writeln!(f, "({0}, {1}, {2}, {3})", row[0], row[1], row[2], row[3])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_f32() {
+        assert_eq!(read_f32_be(&[0x3F, 0x80, 0x00, 0x00]).unwrap(), 1.0);
+        assert_eq!(read_f32_be(&[0xC1, 0x20, 0x00, 0x00, 0xFF]).unwrap(), -10.0);
+        assert!(read_f32_be(&[0x3F, 0x80]).is_err());
+    }
+
+    #[test]
+    fn mtx_from_bytes() {
+        let mut bytes = [0; Mtx::SIZE];
+        // Identity, with m[3][0] = -1.5 and m[3][1] = 2.25.
+        for idx in [0, 5, 10, 15] {
+            bytes[idx * 2 + 1] = 1;
+        }
+        bytes[24..28].copy_from_slice(&[0xFF, 0xFE, 0x00, 0x02]);
+        bytes[0x20 + 24..0x20 + 28].copy_from_slice(&[0x80, 0x00, 0x40, 0x00]);
+        let mtx = Mtx::from_bytes(&bytes).unwrap();
+        assert_eq!(mtx.m, [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [-1.5, 2.25, 0.0, 1.0],
+        ]);
+        assert!(Mtx::from_bytes(&bytes[..Mtx::SIZE - 1]).is_err());
+    }
+}
@@ -1,9 +1,13 @@
-use n64rom::rom::Rom as N64Rom;
+use n64rom::rom::{Endianness, Rom as N64Rom};
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
 use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
-use std::ops::Range;
 use thiserror::Error;
 
-use crate::dma::{self, Entry, Table};
+use crate::codec::Codec;
+use crate::dma::{self, Entry, EntryType, Table};
+use crate::models::object::ObjectFile;
+use crate::util::{self, ConvertRangeExt};
 
 #[derive(Debug, Error)]
 pub enum Error {
@@ -13,15 +17,89 @@ pub enum Error {
     HeaderError(#[from] n64rom::header::Error),
     #[error("{0}")]
     IOError(#[from] io::Error),
+    #[error("{0}")]
+    RomError(#[from] n64rom::rom::Error),
+    #[error("Entry index out-of-range: {0}")]
+    IndexError(usize),
+    #[error("Rom does not contain a dmadata table")]
+    NoTable,
+    #[error("{0}")]
+    CodecError(#[from] crate::codec::Error),
+    #[error("Entry has no file data: {0}")]
+    NoFileData(usize),
+    #[error("Table does not contain an entry for itself")]
+    NoTableEntry,
+    #[error("Table location out-of-range: 0x{0:08X}")]
+    TableOutOfRange(usize),
+    #[error("Table location overlaps file of entry {0}")]
+    TableOverlap(usize),
+    #[error("Entry file is compressed: {0}")]
+    CompressedFile(usize),
+    #[error("Replaced file of entry {0} overlaps the virtual range of entry {1}")]
+    VirtualOverlap(usize, usize),
+    #[error("Replaced file of entry {0} does not fit in the virtual address space")]
+    VirtualOutOfRange(usize),
 }
 
 type Result<T> = ::std::result::Result<T, Error>;
 
+/// Prefix of the build string embedded in Zelda64 roms, such as `zelda@srd44`.
+pub const BUILD_STRING_PREFIX: &[u8] = b"zelda@";
+
+/// Get the name of the file extracted for the entry at the given table index, such as `0002`.
+pub fn file_name(index: usize) -> String {
+    format!("{:04}", index)
+}
+
+/// Kind of rom, as detected by `detect_rom_kind`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RomKind {
+    /// N64 rom with a DMA table.
+    Zelda64,
+    /// Plain N64 rom without a DMA table.
+    N64,
+}
+
+/// Detect whether rom data of any byte order has a DMA table.
+///
+/// The reader is returned to its original position afterwards, so the rom may be read again by the appropriate code
+/// path.
+pub fn detect_rom_kind<R: Read + Seek>(reader: &mut R) -> Result<RomKind> {
+    let position = reader.stream_position()?;
+    let rom = Rom::read(reader)?;
+    reader.seek(SeekFrom::Start(position))?;
+    match rom.table {
+        Some(_) => Ok(RomKind::Zelda64),
+        None => Ok(RomKind::N64),
+    }
+}
+
+/// Read a NUL-terminated printable ASCII string from the beginning of the slice.
+fn read_ascii(bytes: &[u8]) -> &[u8] {
+    let length = bytes.iter().position(|&b| !(b.is_ascii_graphic() || b == b' ')).unwrap_or(bytes.len());
+    match bytes.get(length) {
+        Some(0) | None => &bytes[..length],
+        _ => &[],
+    }
+}
+
+/// Decompressed file data of a table entry, as yielded by `Rom::files`.
+pub struct DecompressedFile {
+    /// Table index of the entry.
+    pub index: usize,
+    /// Decompressed file data.
+    pub data: Vec<u8>,
+}
+
 /// Zelda64 rom.
 pub struct Rom {
     /// Underlying N64 rom.
     pub rom: N64Rom,
     pub table: Option<Table>,
+    /// Decompressed replacement file data of dirty entries, by table index.
+    replaced: BTreeMap<usize, Vec<u8>>,
+    /// Decompressed file data of previously extracted entries, by table index.
+    cache: HashMap<usize, Vec<u8>>,
 }
 
 impl Rom {
@@ -29,15 +107,328 @@ impl Rom {
         Self {
             rom,
             table,
+            replaced: BTreeMap::new(),
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Detect the `Codec` of an entry's file data from its magic.
+    ///
+    /// Returns `None` if the entry has no file data within the rom.
+    pub fn codec(&self, entry: &Entry) -> Option<Codec> {
+        let (range, _) = entry.range_usize();
+        let data = self.rom.full().get(range?)?;
+        Some(Codec::detect(data))
+    }
+
+    /// Whether or not an entry's file data begins with the Yaz0 magic.
+    pub fn is_yaz0(&self, entry: &Entry) -> bool {
+        self.codec(entry) == Some(Codec::Yaz0)
+    }
+
+    /// Get `Entry` at the given table index.
+    pub fn entry(&self, index: usize) -> Result<&Entry> {
+        let table = self.table.as_ref().ok_or(Error::NoTable)?;
+        table.entries.get(index).ok_or(Error::IndexError(index))
+    }
+
+    /// Get decompressed file data of the `Entry` at the given table index.
+    ///
+    /// Returns `None` if the entry has no file data.
+    pub fn extract(&self, index: usize) -> Result<Option<Vec<u8>>> {
+        self.extract_with_limit(index, usize::MAX)
+    }
+
+    /// Get decompressed file data of the `Entry` at the given table index, failing if it would exceed `max_output`
+    /// bytes.
+    ///
+    /// Returns `None` if the entry has no file data.
+    pub fn extract_with_limit(&self, index: usize, max_output: usize) -> Result<Option<Vec<u8>>> {
+        let entry = self.entry(index)?;
+        let (virt, range, kind) = entry.validate()?;
+        match range {
+            // Zero-length files have nothing to decompress, and their stored data may be empty or past the end.
+            Some(_) if virt.is_empty() => Ok(Some(Vec::new())),
+            Some(_) => {
+                let input = self.slice(entry)?;
+                let codec = Codec::from_kind(kind, input)?;
+                let output = codec.decompress_with_limit(input, max_output)?;
+                Ok(Some(output))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Get decompressed file data of the `Entry` at the given table index, keeping it cached for repeated calls.
+    ///
+    /// The cache is cleared by any method of this `Rom` which modifies its data, including `update` and `trim`, but not
+    /// by direct changes to the underlying rom, after which `clear_cache` should be called.
+    pub fn extract_cached(&mut self, index: usize) -> Result<Option<&[u8]>> {
+        if !self.cache.contains_key(&index) {
+            match self.extract(index)? {
+                Some(data) => self.cache.insert(index, data),
+                None => return Ok(None),
+            };
+        }
+        Ok(self.cache.get(&index).map(Vec::as_slice))
+    }
+
+    /// Clear all cached file data.
+    pub fn clear_cache(&mut self) {
+        self.cache.clear();
+    }
+
+    /// Replace the decompressed file data of the `Entry` at the given table index, marking it dirty.
+    ///
+    /// The rom itself is unchanged until `rebuild` is called.
+    pub fn replace_file(&mut self, index: usize, data: Vec<u8>) -> Result<()> {
+        self.entry(index)?;
+        self.replaced.insert(index, data);
+        Ok(())
+    }
+
+    /// Whether or not the file of the `Entry` at the given table index has been replaced since the rom was built.
+    pub fn is_dirty(&self, index: usize) -> bool {
+        self.replaced.contains_key(&index)
+    }
+
+    /// Build a new `Rom` with replaced files, packing files in table order with 16-byte alignment.
+    ///
+    /// Unchanged files are copied verbatim, keeping their original compression. Replaced files are compressed with
+    /// the codec of the file they replace, and keep its virtual start address. Virtual addresses of other files are not
+    /// moved, so a replaced file which would overlap the virtual range of another fails with `Error::VirtualOverlap`.
+    /// Data not covered by any file, other than the table itself, is not kept, and alignment padding between files is
+    /// zeroed.
+    pub fn rebuild(&self) -> Result<Rom> {
+        self.rebuild_with_fill(0)
+    }
+
+    /// Build a new `Rom` with replaced files as with `rebuild`, filling alignment padding between files with the given
+    /// byte instead of zero.
+    pub fn rebuild_with_fill(&self, fill_byte: u8) -> Result<Rom> {
+        let table = self.table.as_ref().ok_or(Error::NoTable)?;
+        let self_index = table.entries.iter().position(|entry| entry.virt_start() == table.address)
+            .ok_or(Error::NoTableEntry)?;
+        let mut image = Vec::with_capacity(self.rom.len());
+        let mut entries = Vec::with_capacity(table.entries.len());
+
+        for (index, entry) in table.entries.iter().enumerate() {
+            let (virt, range, kind) = entry.validate()?;
+            let phys = image.len() as u32;
+            let new_entry = match (self.replaced.get(&index), &range) {
+                (Some(data), _) => {
+                    let codec = match range {
+                        Some(_) => Codec::from_kind(kind, self.slice(entry)?)?,
+                        None => Codec::Raw,
+                    };
+                    let virt_end = u32::try_from(data.len()).ok()
+                        .and_then(|len| virt.start.checked_add(len))
+                        .ok_or(Error::VirtualOutOfRange(index))?;
+                    // Game code refers to files by virtual address, so other files cannot be moved to make room.
+                    let overlap = table.entries.iter().enumerate().position(|(other, entry)| {
+                        other != index && entry.virt_start() < virt_end && virt.start < entry.virt_end()
+                    });
+                    if let Some(other) = overlap {
+                        return Err(Error::VirtualOverlap(index, other));
+                    }
+                    image.extend(codec.compress(data)?);
+                    match codec {
+                        Codec::Raw => Entry::from_uncompressed(virt.start, virt_end, phys),
+                        _ => Entry::from(virt.start, virt_end, phys, image.len() as u32),
+                    }
+                }
+                (None, Some(_)) if index == self_index => {
+                    // Table data is written once all entries are known.
+                    image.resize(image.len() + virt.len().max(table.size()), 0);
+                    Entry::from_uncompressed(virt.start, virt.end, phys)
+                }
+                (None, Some(_)) => {
+                    image.extend_from_slice(self.slice(entry)?);
+                    match kind {
+                        EntryType::Compressed => Entry::from(virt.start, virt.end, phys, image.len() as u32),
+                        _ => Entry::from_uncompressed(virt.start, virt.end, phys),
+                    }
+                }
+                (None, None) => {
+                    entries.push(entry.clone());
+                    continue;
+                }
+            };
+            image.resize(util::align16(image.len() as u32) as usize, fill_byte);
+            entries.push(new_entry);
         }
+
+        let n64rom = N64Rom::from(self.rom.header, self.rom.ipl3, image, self.rom.order());
+        let mut rom = Rom::from(n64rom, Some(Table::from(table.address, entries)));
+        rom.update()?;
+        Ok(rom)
+    }
+
+    /// Iterate over decompressed file data of each table entry, decompressing one file at a time.
+    ///
+    /// Entries without file data are skipped, and a rom without a `Table` yields no files.
+    pub fn files(&self) -> impl Iterator<Item = Result<DecompressedFile>> + '_ {
+        let count = self.table.as_ref().map_or(0, |table| table.entries.len());
+        (0..count).filter_map(move |index| {
+            self.extract(index).transpose().map(|data| data.map(|data| DecompressedFile { index, data }))
+        })
+    }
+
+    /// Find all occurrences of a byte pattern in decompressed files, as `(index, offset)` of each match.
+    ///
+    /// Matches may overlap, and do not span across files. An empty pattern has no matches.
+    pub fn search(&self, pattern: &[u8]) -> Result<Vec<(usize, u32)>> {
+        let mut matches = Vec::new();
+        if pattern.is_empty() {
+            return Ok(matches);
+        }
+        for file in self.files() {
+            let file = file?;
+            let offsets = file.data.windows(pattern.len())
+                .enumerate()
+                .filter(|(_, window)| *window == pattern)
+                .map(|(offset, _)| (file.index, offset as u32));
+            matches.extend(offsets);
+        }
+        Ok(matches)
+    }
+
+    /// Get decompressed file data of the `Entry` at the given table index as an `ObjectFile`.
+    pub fn object(&self, index: usize) -> Result<ObjectFile> {
+        let data = self.extract(index)?.ok_or(Error::NoFileData(index))?;
+        Ok(ObjectFile::from(data))
+    }
+
+    /// Get CRC32 of decompressed file data of the `Entry` at the given table index.
+    ///
+    /// Returns `None` if the entry has no file data.
+    pub fn file_crc32(&self, index: usize) -> Result<Option<u32>> {
+        let file = self.extract(index)?;
+        Ok(file.map(|file| util::crc32(&file)))
+    }
+
+    /// Whether or not the files of two entries are byte-identical after decompression.
+    ///
+    /// Entries without file data are never considered equal.
+    pub fn files_equal(&self, a: usize, b: usize) -> Result<bool> {
+        let a = self.extract(a)?;
+        let b = self.extract(b)?;
+        match (a, b) {
+            (Some(a), Some(b)) => Ok(a == b),
+            _ => Ok(false),
+        }
+    }
+
+    /// Find the build string by scanning for `BUILD_STRING_PREFIX`.
+    ///
+    /// The build name is usually followed by the build date, such as `zelda@srd44 98-10-21 04:56:31`, in which case
+    /// both are returned separated by a space.
+    pub fn find_build_string(&self) -> Option<String> {
+        let full = self.rom.full();
+        let start = full.windows(BUILD_STRING_PREFIX.len()).position(|w| w == BUILD_STRING_PREFIX)?;
+        let name = read_ascii(&full[start..]);
+        if name.is_empty() {
+            return None;
+        }
+        let mut result = String::from_utf8_lossy(name).into_owned();
+        // Date follows after NUL padding.
+        let rest = &full[start + name.len()..];
+        let padding = rest.iter().take(0x10).take_while(|&&b| b == 0).count();
+        let date = read_ascii(&rest[padding..]);
+        if padding > 0 && !date.is_empty() {
+            result.push(' ');
+            result.push_str(&String::from_utf8_lossy(date));
+        }
+        Some(result)
     }
 
     pub fn patch(&mut self, offset: u64, bytes: &[u8]) -> io::Result<usize> {
+        self.clear_cache();
         let mut cursor = Cursor::new(self.rom.data_mut());
         cursor.seek(SeekFrom::Start(offset))?;
         cursor.write(bytes)
     }
 
+    /// Move the `Table` to a new offset in the rom, updating the entry of the table itself.
+    ///
+    /// Note the virtual address of the table changes too: `Table::address` and the virtual range of its own entry are
+    /// set to the new offset. Game code locates `dmadata` by its virtual address, so a relocated retail rom will not
+    /// boot unless its code is patched to use the new address.
+    ///
+    /// The new location must be within the rom and must not overlap the file of any other entry, but may overlap the
+    /// current location. The table is cleared from its current location so it is not found there when read again.
+    pub fn relocate_table(&mut self, new_offset: usize) -> Result<()> {
+        self.clear_cache();
+        let table = self.table.as_mut().ok_or(Error::NoTable)?;
+        let index = table.entries.iter().position(|entry| entry.virt_start() == table.address)
+            .ok_or(Error::NoTableEntry)?;
+        let old = table.entries[index].range_usize().0.ok_or(Error::NoTableEntry)?;
+        let length = old.len().max(table.size());
+        let rom_len = self.rom.len();
+        let end = new_offset.checked_add(length)
+            .filter(|&end| end <= rom_len)
+            .ok_or(Error::TableOutOfRange(new_offset))?;
+        let new = new_offset..end;
+        // Check that no other file is overwritten.
+        for (other, entry) in table.entries.iter().enumerate() {
+            if let (Some(range), _) = entry.range_usize() {
+                if other != index && range.start < new.end && new.start < range.end {
+                    return Err(Error::TableOverlap(other));
+                }
+            }
+        }
+        // Location is within the rom, so fits in 32 bits.
+        let start = new.start as u32;
+        table.entries[index] = Entry::from_uncompressed(start, new.end as u32, start);
+        table.address = start;
+        if let Some(old) = self.rom.full_mut().get_mut(old) {
+            old.fill(0);
+        }
+        self.update_table_data()
+    }
+
+    /// Move file data so that the physical address of each file equals its virtual address, as in a matching rom.
+    ///
+    /// All files must be uncompressed, such as in a rom decompressed without matching. Table data is updated, but CRC
+    /// values are not corrected. Data not covered by any file is not kept.
+    pub fn rematch(&mut self) -> Result<()> {
+        self.clear_cache();
+        let table = self.table.as_ref().ok_or(Error::NoTable)?;
+        let mut entries = Vec::with_capacity(table.entries.len());
+        let mut files = Vec::new();
+        for (index, entry) in table.entries.iter().enumerate() {
+            let (virt, range, kind) = entry.validate()?;
+            match (range, kind) {
+                (Some(_), EntryType::Compressed) => return Err(Error::CompressedFile(index)),
+                (Some(range), _) => {
+                    files.push((index, range.to_usize(), virt.start as usize));
+                    entries.push(Entry::from_uncompressed(virt.start, virt.end, virt.start));
+                }
+                (None, _) => entries.push(entry.clone()),
+            }
+        }
+
+        let end = entries.iter()
+            .filter_map(|entry| entry.range().0)
+            .map(|range| util::align16(range.end) as usize)
+            .max()
+            .unwrap_or(0);
+        let mut image = vec![0; end];
+        for (index, range, start) in files {
+            let data = self.rom.full().get(range).ok_or(Error::NoFileData(index))?;
+            image[start..start + data.len()].copy_from_slice(data);
+        }
+
+        let n64rom = &self.rom;
+        self.rom = N64Rom::from(n64rom.header, n64rom.ipl3, image, n64rom.order());
+        self.table = Some(Table::from(table.address, entries));
+        self.update_table_data()
+    }
+
+    /// Read `Rom` and search for its `Table`.
+    ///
+    /// Rom data of any byte order is accepted: the underlying N64 rom image is always converted to big-endian when
+    /// read, which the `Table` search relies on. The original byte order is kept for writing.
     pub fn read<T: Read>(mut reader: &mut T) -> Result<Self> {
         let n64rom = N64Rom::read(&mut reader)?;
 
@@ -52,26 +443,62 @@ impl Rom {
         Ok(rom)
     }
 
-    pub fn slice(&self, entry: &Entry) -> &[u8] {
-        let (range, _) = entry.range_usize();
-        let range = range.unwrap(); // TODO: Return Result type instead of unwrap range.
-        &self.rom.full()[range]
+    /// Get the file data of an entry as stored in the rom, which is empty for entries without file data.
+    ///
+    /// Fails with `dma::Error::RangeExceedsRom` if the file data is not within the rom, such as for a truncated rom.
+    pub fn slice(&self, entry: &Entry) -> Result<&[u8]> {
+        let (_, range, _) = entry.validate_against(self.rom.len())?;
+        match range {
+            Some(range) => Ok(&self.rom.full()[range.to_usize()]),
+            None => Ok(&[]),
+        }
+    }
+
+    /// Get the size of rom data described by the table, as the 16-byte aligned end of the last file.
+    ///
+    /// The header does not encode the cartridge size, and retail roms are padded to a power of two size after this
+    /// point. Returns `None` if there is no table or no entry has file data.
+    pub fn expected_size(&self) -> Option<usize> {
+        self.table.iter()
+            .flat_map(|table| &table.entries)
+            .filter_map(|entry| entry.range().0)
+            .map(|range| util::align16(range.end) as usize)
+            .max()
     }
 
+    /// Shrink the rom to the 16-byte aligned end of the last file, returning the new length.
+    ///
+    /// Decompressed roms are padded to their full capacity, so this removes the unused space after the last file.
+    pub fn trim(&mut self) -> usize {
+        self.clear_cache();
+        self.rom.truncate(self.expected_size().unwrap_or(0));
+        self.rom.len()
+    }
+
+    /// Update table data and correct CRC values.
     pub fn update(&mut self) -> Result<()> {
+        self.update_with_crc(true)
+    }
+
+    /// Update table data, and optionally correct CRC values with `n64rom::rom::Rom::finalize`.
+    pub fn update_with_crc(&mut self, correct_crc: bool) -> Result<()> {
         self.update_table_data()?;
-        // Correct CRC values
-        self.rom.correct_crc();
+        if correct_crc {
+            self.rom.finalize()?;
+        }
         Ok(())
     }
 
     fn update_table_data(&mut self) -> Result<()> {
+        // Rewrites the table file, and CRC values in the head are usually corrected afterwards.
+        self.clear_cache();
         match &self.table {
             Some(table) => {
-                let offset = table.address as usize;
-                let range = Range { start: offset, end: offset + table.size() };
-                let mut slice = &mut self.rom.full_mut()[range];
-                table.write(&mut slice)?;
+                // Table data is at the physical start of its own entry, which may differ from its address once rebuilt.
+                let offset = table.self_entry()
+                    .map_or(table.address, |entry| entry.phys_start()) as usize;
+                let slice = self.rom.full_mut().get_mut(offset..).unwrap_or_default();
+                table.write_into(slice)?;
                 Ok(())
             }
             None => Ok(()),
@@ -82,9 +509,318 @@ impl Rom {
         self.rom.write(&mut writer, None)
     }
 
-    pub fn write_with_update<T: Seek + Write>(&mut self, mut writer: &mut T) -> Result<usize> {
+    /// Write `Rom` data to writer in the given byte order, rather than the order it was read in.
+    pub fn write_as<T: Write>(&mut self, mut writer: &mut T, order: Endianness) -> io::Result<usize> {
+        self.rom.write(&mut writer, Some(order))
+    }
+
+    /// Write `Rom` data to writer after updating table data and correcting CRC values.
+    pub fn write_with_update<T: Seek + Write>(&mut self, writer: &mut T) -> Result<usize> {
+        self.write_with_update_crc(writer, true)
+    }
+
+    /// Write `Rom` data to a writer which may not support seeking, such as stdout, after updating table data and
+    /// correcting CRC values.
+    ///
+    /// Updates are applied to the in-memory image before any data is written.
+    pub fn write_with_update_to<T: Write>(&mut self, mut writer: &mut T) -> Result<usize> {
         self.update()?;
         let written = self.write(&mut writer)?;
         Ok(written)
     }
+
+    /// Write `Rom` data to writer after updating table data, and optionally correcting CRC values.
+    pub fn write_with_update_crc<T: Seek + Write>(&mut self, mut writer: &mut T, correct_crc: bool) -> Result<usize> {
+        self.update_with_crc(correct_crc)?;
+        let written = self.write(&mut writer)?;
+        Ok(written)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing;
+
+    #[test]
+    fn files_equal() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let other = [1, 2, 3];
+        let rom = testing::build_rom(&[(&data, true), (&data, false), (&other, false)]);
+        assert!(rom.files_equal(2, 3).unwrap());
+        assert!(rom.files_equal(3, 2).unwrap());
+        assert!(!rom.files_equal(2, 4).unwrap());
+        assert!(matches!(rom.files_equal(2, 5), Err(Error::IndexError(5))));
+    }
+
+    #[test]
+    fn read_byte_orders() {
+        use n64rom::convert;
+        use n64rom::rom::Endianness;
+
+        let data: Vec<u8> = (0..0x80).collect();
+        let (image, entries) = testing::build_image(&[(&data, true), (&[1, 2, 3], false)]);
+        for &order in &[Endianness::Big, Endianness::Little, Endianness::Mixed] {
+            let mut converted = image.clone();
+            convert::convert(&mut converted, Endianness::Big, order).unwrap();
+            let mut rom = Rom::read(&mut Cursor::new(converted.clone())).unwrap();
+            assert_eq!(rom.rom.order(), order);
+            assert_eq!(rom.rom.full(), &image[..]);
+            assert_eq!(rom.table.as_ref().unwrap().entries, entries);
+            assert_eq!(rom.extract(2).unwrap().unwrap(), data);
+
+            // Written in original byte order.
+            let mut output = Vec::new();
+            rom.write(&mut output).unwrap();
+            assert_eq!(output, converted);
+        }
+    }
+
+    #[test]
+    fn detect_rom_kind() {
+        let (mut image, _) = testing::build_image(&[(&[1, 2, 3], false)]);
+        let mut cursor = Cursor::new(image.clone());
+        assert_eq!(super::detect_rom_kind(&mut cursor).unwrap(), RomKind::Zelda64);
+        assert_eq!(cursor.position(), 0);
+
+        // Without the initial entry, no table is found.
+        image[0x1064..0x1068].fill(0);
+        assert_eq!(super::detect_rom_kind(&mut Cursor::new(image)).unwrap(), RomKind::N64);
+    }
+
+    #[test]
+    fn rebuild() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let other: Vec<u8> = (0..0x80).collect();
+        let mut rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false), (&other, true)]);
+        let compressed = rom.slice(rom.entry(4).unwrap()).unwrap().to_vec();
+        // Replaced files must fit before the virtual start of the next file, at 0x11C0.
+        rom.replace_file(3, (0..0x30).collect()).unwrap();
+        assert!(matches!(rom.rebuild(), Err(Error::VirtualOverlap(3, 4))));
+        let replacement: Vec<u8> = (0..0x10).collect();
+        rom.replace_file(3, replacement.clone()).unwrap();
+        rom.replace_file(2, other.clone()).unwrap();
+        assert!(rom.is_dirty(3));
+        assert!(!rom.is_dirty(4));
+        assert!(matches!(rom.replace_file(5, Vec::new()), Err(Error::IndexError(5))));
+
+        let rebuilt = rom.rebuild().unwrap();
+        assert!(rebuilt.rom.check_crc().0);
+        assert!(!rebuilt.is_dirty(3));
+        // Replaced files keep their codec, unchanged files are copied verbatim.
+        assert!(rebuilt.entry(2).unwrap().is_compressed());
+        assert_eq!(rebuilt.extract(2).unwrap().unwrap(), other);
+        assert!(rebuilt.entry(3).unwrap().is_decompressed());
+        assert_eq!(rebuilt.extract(3).unwrap().unwrap(), replacement);
+        assert_eq!(rebuilt.slice(rebuilt.entry(4).unwrap()).unwrap(), &compressed[..]);
+        assert_eq!(rebuilt.entry(4).unwrap().virt(), rom.entry(4).unwrap().virt());
+        let entries = &rebuilt.table.as_ref().unwrap().entries;
+        for (index, entry) in entries.iter().enumerate() {
+            assert!(entries[index + 1..].iter().all(|other| entry.virt_end() <= other.virt_start()));
+        }
+
+        let reread = Rom::read(&mut Cursor::new(rebuilt.rom.full())).unwrap();
+        assert_eq!(reread.table.unwrap().entries, rebuilt.table.unwrap().entries);
+    }
+
+    #[test]
+    fn rebuild_with_fill() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let mut rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false), (&[4], false)]);
+        rom.replace_file(3, vec![5; 0xD]).unwrap();
+        let rebuilt = rom.rebuild_with_fill(0xFF).unwrap();
+        let gaps = testing::uncovered_bytes(&rebuilt);
+        assert!(!gaps.is_empty());
+        assert!(gaps.iter().all(|&b| b == 0xFF));
+        assert_eq!(rebuilt.extract(3).unwrap().unwrap(), vec![5; 0xD]);
+        assert!(testing::uncovered_bytes(&rom.rebuild().unwrap()).iter().all(|&b| b == 0));
+    }
+
+    #[test]
+    fn files() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let mut rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false)]);
+        let files: Vec<DecompressedFile> = rom.files().collect::<Result<_>>().unwrap();
+        let indexes: Vec<usize> = files.iter().map(|file| file.index).collect();
+        assert_eq!(indexes, vec![0, 1, 2, 3]);
+        assert_eq!(files[2].data, data);
+        assert_eq!(files[3].data, vec![1, 2, 3]);
+
+        rom.table = None;
+        assert_eq!(rom.files().count(), 0);
+    }
+
+    #[test]
+    fn search() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let rom = testing::build_rom(&[(&data, true), (&[1, 1, 1, 2], false)]);
+        assert_eq!(rom.search(&[0x10, 0x11]).unwrap(), vec![(2, 0x32)]);
+        assert_eq!(rom.search(&[1, 1]).unwrap(), vec![(2, 3), (2, 4), (3, 0), (3, 1)]);
+        assert!(rom.search(&[0xFF, 0xFF]).unwrap().is_empty());
+        assert!(rom.search(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rematch() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        assert!(matches!(testing::build_rom(&[(&data, true)]).rematch(), Err(Error::CompressedFile(2))));
+
+        // Squeezed layout, where the last file is virtually after a gap.
+        let mut rom = testing::build_rom(&[(&data, false), (&[1, 2, 3], false)]);
+        let phys = rom.entry(3).unwrap().phys_start();
+        rom.table.as_mut().unwrap().entries[3] = Entry::from_uncompressed(0x2000, 0x2003, phys);
+        rom.rematch().unwrap();
+        for entry in &rom.table.as_ref().unwrap().entries {
+            assert_eq!(entry.phys_start(), entry.virt_start());
+        }
+        assert_eq!(rom.rom.len(), 0x2010);
+        assert_eq!(rom.extract(2).unwrap().unwrap(), data);
+        assert_eq!(&rom.rom.full()[0x2000..0x2003], &[1, 2, 3]);
+        let reread = Rom::read(&mut Cursor::new(rom.rom.full())).unwrap();
+        assert_eq!(reread.table.unwrap().entries, rom.table.unwrap().entries);
+    }
+
+    #[test]
+    fn extract_cached() {
+        let data: Vec<u8> = (0..0x100).map(|i| (i / 3) as u8).collect();
+        let mut rom = testing::build_rom(&[(&data, true), (&[1, 2, 3], false)]);
+        assert_eq!(rom.extract_cached(2).unwrap(), Some(&data[..]));
+        assert_eq!(rom.cache.len(), 1);
+        assert_eq!(rom.extract_cached(2).unwrap(), Some(&data[..]));
+        assert!(matches!(rom.extract_cached(5), Err(Error::IndexError(5))));
+
+        // Patching file data clears the cache.
+        let offset = (rom.entry(3).unwrap().phys_start() as usize - n64rom::rom::HEAD_SIZE) as u64;
+        assert_eq!(rom.extract_cached(3).unwrap(), Some(&[1, 2, 3][..]));
+        rom.patch(offset, &[4]).unwrap();
+        assert!(rom.cache.is_empty());
+        assert_eq!(rom.extract_cached(3).unwrap(), Some(&[4, 2, 3][..]));
+        rom.clear_cache();
+        assert!(rom.cache.is_empty());
+    }
+
+    #[test]
+    fn extract_cached_update() {
+        let mut rom = testing::build_rom(&[(&[1, 2, 3], false)]);
+        let head = rom.extract_cached(0).unwrap().unwrap().to_vec();
+        let table = rom.extract_cached(1).unwrap().unwrap().to_vec();
+
+        // Updating rewrites the table file and the CRC values in the head.
+        rom.table.as_mut().unwrap().entries[2] = Entry::from_uncompressed(0x1090, 0x1092, 0x1090);
+        rom.update().unwrap();
+        assert_ne!(rom.extract_cached(0).unwrap().unwrap(), &head[..]);
+        let expected = rom.table.as_ref().unwrap().to_vec();
+        assert_eq!(rom.extract_cached(1).unwrap().unwrap(), &expected[..]);
+        assert_ne!(rom.extract_cached(1).unwrap().unwrap(), &table[..]);
+
+        rom.extract_cached(2).unwrap();
+        rom.trim();
+        assert!(rom.cache.is_empty());
+    }
+
+    #[test]
+    fn slice_truncated() {
+        let (image, _) = testing::build_image(&[(&[1, 2, 3], false)]);
+        let rom = Rom::read(&mut Cursor::new(&image[..0x1090])).unwrap();
+        assert!(rom.slice(rom.entry(1).unwrap()).is_ok());
+        assert!(matches!(rom.extract(2), Err(Error::DMAError(dma::Error::RangeExceedsRom(_, 0x1090)))));
+    }
+
+    #[test]
+    fn codec() {
+        let data: Vec<u8> = (0..0x40).collect();
+        let rom = testing::build_rom(&[(&data, true), (b"Yay0\0\0\0\x10", false), (&data, false)]);
+        let entries = &rom.table.as_ref().unwrap().entries;
+        assert!(rom.is_yaz0(&entries[2]));
+        assert_eq!(rom.codec(&entries[3]), Some(Codec::Yay0));
+        assert_eq!(rom.codec(&entries[4]), Some(Codec::Raw));
+        assert_eq!(rom.codec(&Entry::default()), None);
+    }
+
+    #[test]
+    fn find_build_string() {
+        let data = b"\x01\x02zelda@srd44\0\0\0\0\098-10-21 04:56:31\0\0";
+        let rom = testing::build_rom(&[(data, false)]);
+        assert_eq!(rom.find_build_string().as_deref(), Some("zelda@srd44 98-10-21 04:56:31"));
+
+        let rom = testing::build_rom(&[(b"zelda@srd022j\0\xFF", false)]);
+        assert_eq!(rom.find_build_string().as_deref(), Some("zelda@srd022j"));
+
+        let rom = testing::build_rom(&[(b"no build", false)]);
+        assert_eq!(rom.find_build_string(), None);
+    }
+
+    #[test]
+    fn write_as() {
+        let mut rom = testing::build_rom(&[(&[1, 2, 3, 4], false)]);
+        let mut big = Vec::new();
+        rom.write(&mut big).unwrap();
+        let mut little = Vec::new();
+        rom.write_as(&mut little, Endianness::Little).unwrap();
+        assert_eq!(little.len(), big.len());
+        assert_eq!(&little[..4], &[0x40, 0x12, 0x37, 0x80]);
+        let read = Rom::read(&mut Cursor::new(little)).unwrap();
+        assert_eq!(read.rom.order(), Endianness::Little);
+        assert_eq!(read.rom.full(), &big[..]);
+    }
+
+    #[test]
+    fn relocate_table() {
+        let (mut image, _) = testing::build_image(&[(&[1; 0x10], false)]);
+        image.resize(0x1100, 0);
+        let mut rom = Rom::read(&mut Cursor::new(image)).unwrap();
+        assert!(matches!(rom.relocate_table(0x1080), Err(Error::TableOverlap(2))));
+        assert!(matches!(rom.relocate_table(0x10F0), Err(Error::TableOutOfRange(0x10F0))));
+        assert!(matches!(rom.relocate_table(usize::MAX - 0x10), Err(Error::TableOutOfRange(_))));
+
+        rom.relocate_table(0x10C0).unwrap();
+        let mut output = Vec::new();
+        rom.write(&mut output).unwrap();
+        assert!(output[0x1060..0x1090].iter().all(|&b| b == 0));
+        let read = Rom::read(&mut Cursor::new(output)).unwrap();
+        let table = read.table.as_ref().unwrap();
+        assert_eq!(table.address, 0x10C0);
+        assert_eq!(table.entries[1], Entry::from_uncompressed(0x10C0, 0x10F0, 0x10C0));
+        assert_eq!(read.extract(2).unwrap().unwrap(), vec![1; 0x10]);
+    }
+
+    #[test]
+    fn file_crc32() {
+        // CRC32 of ASCII "123456789" is the standard check value.
+        let data = b"123456789";
+        let rom = testing::build_rom(&[(data, true), (data, false)]);
+        assert_eq!(rom.file_crc32(2).unwrap(), Some(0xCBF4_3926));
+        assert_eq!(rom.file_crc32(3).unwrap(), Some(0xCBF4_3926));
+        assert!(matches!(rom.file_crc32(4), Err(Error::IndexError(4))));
+    }
+
+    #[test]
+    fn trim() {
+        let (mut image, _) = testing::build_image(&[(&[1; 0x18], false)]);
+        image.resize(0x4000, 0);
+        let mut rom = Rom::read(&mut Cursor::new(image)).unwrap();
+        assert_eq!(rom.trim(), 0x10B0);
+        assert_eq!(rom.rom.len(), 0x10B0);
+        assert_eq!(rom.extract(2).unwrap().unwrap(), vec![1; 0x18]);
+    }
+
+    #[test]
+    fn expected_size() {
+        let mut rom = testing::build_rom(&[(&[1; 0x18], false)]);
+        assert_eq!(rom.expected_size(), Some(0x10B0));
+        rom.table = None;
+        assert_eq!(rom.expected_size(), None);
+    }
+
+    #[test]
+    fn write_with_update_to() {
+        let mut rom = testing::build_rom(&[(&[1; 0x10], false)]);
+        rom.table.as_mut().unwrap().entries[2] = Entry::from_uncompressed(0x1090, 0x1098, 0x1090);
+        // `Vec` does not implement `Seek`.
+        let mut output: Vec<u8> = Vec::new();
+        rom.write_with_update_to(&mut output).unwrap();
+        let read = Rom::read(&mut Cursor::new(output)).unwrap();
+        assert_eq!(read.entry(2).unwrap(), &Entry::from_uncompressed(0x1090, 0x1098, 0x1090));
+        assert!(read.rom.check_crc().0);
+    }
 }
@@ -16,6 +16,7 @@ impl<T> Relative<T> {
 }
 
 /// Segmented address, with an 8-bit segment index and 24-bit offset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
 pub struct SegAddr(u32);
 
 impl SegAddr {
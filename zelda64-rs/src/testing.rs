@@ -0,0 +1,73 @@
+//! Helpers for building synthetic roms in tests.
+
+use n64rom::header::Magic;
+use std::io::Cursor;
+use yaz0::{CompressionLevel, Yaz0Writer};
+
+use crate::dma::Entry;
+use crate::rom::Rom;
+use crate::util;
+
+/// Address of the table in roms built by `build_image`.
+pub const TABLE_ADDRESS: u32 = 0x1060;
+
+/// Compress data with Yaz0.
+pub fn yaz0(data: &[u8]) -> Vec<u8> {
+    let mut compressed = Vec::new();
+    let writer = Yaz0Writer::new(&mut compressed);
+    writer.compress_and_write(data, CompressionLevel::Naive { quality: 10 }).unwrap();
+    compressed
+}
+
+/// Build a rom image with a table at `TABLE_ADDRESS`, followed by the given files as `(data, compressed)`.
+///
+/// The first two entries describe the head and the table itself. Files follow the table and are 16-byte aligned both
+/// virtually and physically, so compressed files shift the physical addresses of all files after them.
+pub fn build_image(files: &[(&[u8], bool)]) -> (Vec<u8>, Vec<Entry>) {
+    let table_size = ((files.len() + 2) * 0x10) as u32;
+    let mut image = vec![0; TABLE_ADDRESS as usize];
+    image[..4].copy_from_slice(Magic::new().as_ref());
+    let mut entries = vec![
+        Entry::initial(),
+        Entry::from_uncompressed(TABLE_ADDRESS, TABLE_ADDRESS + table_size, TABLE_ADDRESS),
+    ];
+    image.resize((TABLE_ADDRESS + table_size) as usize, 0);
+    let mut virt = TABLE_ADDRESS + table_size;
+    for &(data, compressed) in files {
+        let phys = image.len() as u32;
+        if compressed {
+            image.extend(yaz0(data));
+            image.resize(util::align16(image.len() as u32) as usize, 0);
+            entries.push(Entry::from(virt, virt + data.len() as u32, phys, image.len() as u32));
+        } else {
+            image.extend(data);
+            image.resize(util::align16(image.len() as u32) as usize, 0);
+            entries.push(Entry::from_uncompressed(virt, virt + data.len() as u32, phys));
+        }
+        virt = util::align16(virt + data.len() as u32);
+    }
+    let mut slice = &mut image[TABLE_ADDRESS as usize..(TABLE_ADDRESS + table_size) as usize];
+    for entry in &entries {
+        entry.write(&mut slice).unwrap();
+    }
+    (image, entries)
+}
+
+/// Build a `Rom` using `build_image`.
+pub fn build_rom(files: &[(&[u8], bool)]) -> Rom {
+    let (image, _) = build_image(files);
+    Rom::read(&mut Cursor::new(image)).unwrap()
+}
+
+/// Get the bytes of a rom which are not covered by the physical range of any table entry, such as alignment padding.
+pub fn uncovered_bytes(rom: &Rom) -> Vec<u8> {
+    let data = rom.rom.full();
+    let mut covered = vec![false; data.len()];
+    for entry in &rom.table.as_ref().unwrap().entries {
+        if let Some(range) = entry.range().0 {
+            let end = (range.end as usize).min(data.len());
+            covered[(range.start as usize).min(end)..end].fill(true);
+        }
+    }
+    data.iter().zip(covered).filter(|(_, covered)| !covered).map(|(&b, _)| b).collect()
+}
@@ -1,4 +1,3 @@
-use std::convert::TryInto;
 use std::ops::Range;
 
 /// Align to 16-byte boundary.
@@ -6,6 +5,11 @@ pub fn align16(value: u32) -> u32 {
     (value + 0xF) & !0xF
 }
 
+/// Compute CRC32 of data.
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32fast::hash(data)
+}
+
 pub fn to_signed_hex(n: isize) -> String {
     if n < 0 {
         format!("-{:X}", -n)
@@ -20,8 +24,8 @@ pub trait ConvertRangeExt {
 
 impl ConvertRangeExt for Range<u32> {
     fn to_usize(&self) -> Range<usize> {
-        let start: usize = (self.start as usize).try_into().unwrap();
-        let end: usize = (self.end as usize).try_into().unwrap();
+        let start: usize = self.start as usize;
+        let end: usize = self.end as usize;
         Range { start, end }
     }
 }